@@ -0,0 +1,20 @@
+//! Feeds arbitrary text to the parser of `sensors -j` output, which has to
+//! return an error instead of panicking. The parser is a binary crate module,
+//! so it is included with the modules it uses.
+#![no_main]
+#![allow(dead_code)]
+
+use libfuzzer_sys::fuzz_target;
+
+#[path = "../../src/access.rs"]
+mod access;
+#[path = "../../src/error.rs"]
+mod error;
+#[path = "../../src/sensors.rs"]
+mod sensors;
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(json_str) = std::str::from_utf8(data) {
+        let _ = sensors::parse_adapters(json_str);
+    }
+});
@@ -0,0 +1,240 @@
+use std::ffi::CString;
+use std::os::unix::ffi::OsStrExt;
+use std::os::unix::fs::MetadataExt;
+use std::path::Path;
+
+pub static PATH_FAN: &str = "/proc/acpi/ibm/fan";
+pub static PATH_MODULE_FAN_CONTROL: &str = "/sys/module/thinkpad_acpi/parameters/fan_control";
+/// Lockdown mode of the kernel, e.g. "none [integrity] confidentiality"
+pub static PATH_LOCKDOWN: &str = "/sys/kernel/security/lockdown";
+
+/// Why fan commands cannot be written
+#[derive(Debug, Clone, PartialEq)]
+pub enum Denied {
+    /// thinkpad_acpi is not loaded or the model has no fan file
+    NotLoaded,
+    /// thinkpad_acpi is loaded without `fan_control=1`, with the lockdown
+    /// mode of the kernel if it is locked down
+    FanControlOff { lockdown: Option<String> },
+    /// The fan file is not writable by the user
+    Ownership { uid: u32, mode: u32 },
+}
+
+impl Denied {
+    /// Short reason, e.g. for the read-only line of the Fan Info block
+    pub fn reason(&self) -> String {
+        match self {
+            Denied::NotLoaded => "thinkpad_acpi is not loaded".to_string(),
+            Denied::FanControlOff { lockdown: None } => {
+                "thinkpad_acpi fan_control=1 is not set".to_string()
+            }
+            Denied::FanControlOff {
+                lockdown: Some(mode),
+            } => format!(
+                "thinkpad_acpi fan_control=1 is not set, lockdown is {}",
+                mode
+            ),
+            Denied::Ownership { uid, mode } => format!(
+                "{} is owned by {} with mode {:04o}",
+                PATH_FAN,
+                owner_name(*uid),
+                mode & 0o7777
+            ),
+        }
+    }
+
+    /// Explanation and the commands fixing the problem, one per line
+    pub fn steps(&self) -> Vec<String> {
+        match self {
+            Denied::NotLoaded => vec![
+                format!("{} does not exist. Load the driver with:", PATH_FAN),
+                "  sudo modprobe thinkpad_acpi fan_control=1".to_string(),
+            ],
+            Denied::FanControlOff { lockdown } => {
+                let mut steps = vec![
+                    "Fan control is disabled in the driver. Reload it with:".to_string(),
+                    "  sudo modprobe -r thinkpad_acpi".to_string(),
+                    "  sudo modprobe thinkpad_acpi fan_control=1".to_string(),
+                ];
+                if let Some(mode) = lockdown {
+                    steps.push(format!(
+                        "The kernel is in {} lockdown, usually because of secure \
+                         boot, which may refuse the fan_control parameter.",
+                        mode
+                    ));
+                }
+                steps
+            }
+            Denied::Ownership { .. } => vec![
+                format!("{} is not writable by {}.", PATH_FAN, whoami::username()),
+                "Install the helper or join the thinkfan group, see \
+                 Fan Permissions in the README, or change the owner until \
+                 the next reboot with:"
+                    .to_string(),
+                format!("  sudo chown {} {}", whoami::username(), PATH_FAN),
+            ],
+        }
+    }
+}
+
+/// State of the files deciding whether the fan can be controlled
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct State {
+    /// Owner and mode of the fan file, `None` if it does not exist
+    pub fan: Option<(u32, u32)>,
+    pub writable: bool,
+    /// Value of the `fan_control` parameter, `None` if it is not readable
+    pub fan_control: Option<bool>,
+    pub lockdown: Option<String>,
+}
+
+impl State {
+    /// Reads the state of the files without opening the fan file for
+    /// writing
+    pub fn read(fan: &Path, fan_control: &Path, lockdown: &Path) -> Self {
+        let param = std::fs::read_to_string(fan_control).ok();
+        Self {
+            fan: std::fs::metadata(fan)
+                .ok()
+                .map(|metadata| (metadata.uid(), metadata.mode())),
+            writable: is_writable(fan),
+            // Some kernels use Y/N, others 1/0
+            fan_control: param.map(|value| matches!(value.trim(), "Y" | "1")),
+            lockdown: std::fs::read_to_string(lockdown)
+                .ok()
+                .and_then(|content| parse_lockdown(&content)),
+        }
+    }
+
+    /// The first problem preventing fan control, if any
+    pub fn check(&self) -> Result<(), Denied> {
+        let Some((uid, mode)) = self.fan else {
+            return Err(Denied::NotLoaded);
+        };
+        if self.fan_control == Some(false) {
+            return Err(Denied::FanControlOff {
+                lockdown: self.lockdown.clone(),
+            });
+        }
+        if !self.writable {
+            return Err(Denied::Ownership { uid, mode });
+        }
+        Ok(())
+    }
+}
+
+/// Checks whether fan commands can be written to `/proc/acpi/ibm/fan`
+pub fn check() -> Result<(), Denied> {
+    State::read(
+        Path::new(PATH_FAN),
+        Path::new(PATH_MODULE_FAN_CONTROL),
+        Path::new(PATH_LOCKDOWN),
+    )
+    .check()
+}
+
+/// Whether the user may write to a file, as decided by the kernel
+fn is_writable(path: &Path) -> bool {
+    let Ok(path) = CString::new(path.as_os_str().as_bytes()) else {
+        return false;
+    };
+    // SAFETY: the path is a valid NUL terminated string
+    unsafe { libc::access(path.as_ptr(), libc::W_OK) == 0 }
+}
+
+/// Active lockdown mode, `None` if the kernel is not locked down
+fn parse_lockdown(content: &str) -> Option<String> {
+    let mode = content.split_whitespace().find_map(|word| {
+        word.strip_prefix('[')
+            .and_then(|word| word.strip_suffix(']'))
+    })?;
+    (mode != "none").then(|| mode.to_string())
+}
+
+fn owner_name(uid: u32) -> String {
+    if uid == 0 {
+        "root".to_string()
+    } else {
+        format!("uid {}", uid)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lockdown() {
+        assert_eq!(parse_lockdown("[none] integrity confidentiality\n"), None);
+        assert_eq!(
+            parse_lockdown("none [integrity] confidentiality\n").as_deref(),
+            Some("integrity")
+        );
+        assert_eq!(parse_lockdown(""), None);
+    }
+
+    #[test]
+    fn problems() {
+        assert_eq!(State::default().check(), Err(Denied::NotLoaded));
+
+        let state = State {
+            fan: Some((0, 0o100644)),
+            writable: false,
+            fan_control: Some(false),
+            lockdown: Some("integrity".into()),
+        };
+        let denied = state.check().unwrap_err();
+        assert_eq!(
+            denied.reason(),
+            "thinkpad_acpi fan_control=1 is not set, lockdown is integrity"
+        );
+        assert_eq!(denied.steps().len(), 4);
+
+        let state = State {
+            fan_control: Some(true),
+            ..state
+        };
+        let denied = state.check().unwrap_err();
+        assert_eq!(
+            denied,
+            Denied::Ownership {
+                uid: 0,
+                mode: 0o100644
+            }
+        );
+        assert_eq!(
+            denied.reason(),
+            "/proc/acpi/ibm/fan is owned by root with mode 0644"
+        );
+
+        // An unreadable parameter does not mean fan control is off
+        let state = State {
+            writable: true,
+            fan_control: None,
+            ..state
+        };
+        assert_eq!(state.check(), Ok(()));
+    }
+
+    #[test]
+    fn read_state() {
+        let dir = std::env::temp_dir().join(format!("thinkfan-tui-access-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let fan = dir.join("fan");
+        std::fs::write(&fan, "level:\t\tauto\n").unwrap();
+        std::fs::write(dir.join("fan_control"), "Y\n").unwrap();
+
+        let state = State::read(&fan, &dir.join("fan_control"), &dir.join("lockdown"));
+        assert!(state.fan.is_some());
+        assert_eq!(state.fan_control, Some(true));
+        assert_eq!(state.lockdown, None);
+        // Nothing was truncated
+        assert_eq!(std::fs::read_to_string(&fan).unwrap(), "level:\t\tauto\n");
+
+        let state = State::read(&dir.join("missing"), &dir.join("missing"), &dir);
+        assert_eq!(state.check(), Err(Denied::NotLoaded));
+        assert!(!state.writable);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,645 @@
+use std::collections::HashMap;
+use std::io::Write;
+use std::process::{Command, Stdio};
+use std::time::{Duration, Instant};
+
+use serde::Deserialize;
+
+use crate::dbus;
+use crate::fault::{Fault, FaultDetector};
+use crate::units::Unit;
+
+/// Degrees (°C) a sensor has to drop below a level before it is cleared, so
+/// that readings around a level do not raise alert after alert
+const HYSTERESIS: f64 = 2.0;
+
+/// Id used for the fan in events
+pub static FAN: &str = "fan";
+
+/// Key of the fan fault state, separate from the stopped fan
+static FAN_FAULT: &str = "fan-fault";
+
+/// Level written to the fan when a fault is detected
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Fallback {
+    FullSpeed,
+    Auto,
+}
+
+impl Fallback {
+    /// Command written to `/proc/acpi/ibm/fan`
+    pub fn command(self) -> &'static str {
+        match self {
+            Fallback::FullSpeed => "level full-speed",
+            Fallback::Auto => "level auto",
+        }
+    }
+}
+
+/// Levels of a sensor, in the configured unit
+#[derive(Debug, Default, Clone, Copy, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Levels {
+    pub warning: Option<f64>,
+    pub critical: Option<f64>,
+}
+
+/// The `[alerts]` section of the config file. No temperature alerts are
+/// raised unless a level is set, fan faults are watched for by default.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AlertsConfig {
+    pub warning: Option<f64>,
+    pub critical: Option<f64>,
+    /// Ring the terminal bell
+    pub bell: bool,
+    /// Show a desktop notification
+    pub notify: bool,
+    /// Shell command run for every event
+    pub hook: Option<String>,
+    /// Least number of seconds between repeated alerts of a sensor
+    pub interval: u64,
+    /// Watch the fan for stalls and missing reactions, off by default as the
+    /// heuristics may misfire on some models
+    pub fan_fault: bool,
+    /// Seconds the fan is given to react to a new level
+    pub fan_response: u64,
+    /// Level set when a fan fault is detected
+    pub fan_fallback: Option<Fallback>,
+    /// Levels of single sensors by id, e.g. "coretemp-isa-0000/Package id 0"
+    pub sensors: HashMap<String, Levels>,
+}
+
+impl Default for AlertsConfig {
+    fn default() -> Self {
+        Self {
+            warning: None,
+            critical: None,
+            bell: true,
+            notify: true,
+            hook: None,
+            interval: 60,
+            fan_fault: false,
+            fan_response: 10,
+            fan_fallback: None,
+            sensors: HashMap::new(),
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Level {
+    #[default]
+    Normal,
+    Warning,
+    Critical,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Kind {
+    Warning,
+    Critical,
+    /// A sensor went back below its levels
+    Clear,
+    /// The fan reports 0 RPM while a sensor is above its warning level
+    FanStopped,
+    FanRunning,
+    /// The fan does not do what it is told, see `Event::fault`
+    FanFault,
+    /// The speed of a faulty fan changed again
+    FanRecovered,
+}
+
+impl Kind {
+    /// Name used in the hook environment
+    pub fn name(self) -> &'static str {
+        match self {
+            Kind::Warning => "warning",
+            Kind::Critical => "critical",
+            Kind::Clear => "clear",
+            Kind::FanStopped => "fan-stopped",
+            Kind::FanRunning => "fan-running",
+            Kind::FanFault => "fan-fault",
+            Kind::FanRecovered => "fan-recovered",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Event {
+    pub kind: Kind,
+    /// Sensor id, or `FAN` for fan events
+    pub sensor: String,
+    /// Display name of the sensor, the hottest one for fan events
+    pub name: String,
+    /// Temperature in °C
+    pub temp: f64,
+    pub rpm: Option<u32>,
+    pub fault: Option<Fault>,
+}
+
+/// A temperature reading checked against the levels
+#[derive(Debug, Clone, Copy)]
+pub struct Reading<'a> {
+    pub id: &'a str,
+    pub name: &'a str,
+    pub temp: f64,
+}
+
+#[derive(Debug, Default)]
+struct State {
+    level: Level,
+    /// Level and time of the last alert that was let through
+    last_alert: Option<(Level, Instant)>,
+    /// An alert was let through and has not been cleared yet
+    active: bool,
+}
+
+/// Tracks the levels of the sensors and turns changes into events
+#[derive(Debug)]
+pub struct Alerts {
+    config: AlertsConfig,
+    /// Default levels in °C
+    levels: (Option<f64>, Option<f64>),
+    /// Levels of single sensors in °C
+    sensor_levels: HashMap<String, (Option<f64>, Option<f64>)>,
+    states: HashMap<String, State>,
+    faults: FaultDetector,
+}
+
+impl Default for Alerts {
+    fn default() -> Self {
+        Self::new(AlertsConfig::default(), Unit::default())
+    }
+}
+
+impl Alerts {
+    pub fn new(config: AlertsConfig, unit: Unit) -> Self {
+        let to_celsius = |levels: &Levels| {
+            (
+                levels.warning.map(|t| unit.to_celsius(t)),
+                levels.critical.map(|t| unit.to_celsius(t)),
+            )
+        };
+        Self {
+            levels: to_celsius(&Levels {
+                warning: config.warning,
+                critical: config.critical,
+            }),
+            sensor_levels: config
+                .sensors
+                .iter()
+                .map(|(id, levels)| (id.clone(), to_celsius(levels)))
+                .collect(),
+            faults: FaultDetector::new(Duration::from_secs(config.fan_response)),
+            config,
+            states: HashMap::new(),
+        }
+    }
+
+    /// Warning and critical level of a sensor in °C, falling back to the
+    /// default levels
+    fn levels(&self, id: &str) -> (Option<f64>, Option<f64>) {
+        match self.sensor_levels.get(id) {
+            Some((warning, critical)) => (warning.or(self.levels.0), critical.or(self.levels.1)),
+            None => self.levels,
+        }
+    }
+
+    /// Level of a reading given the current level, readings have to drop
+    /// below a level by `HYSTERESIS` to leave it
+    fn level(&self, id: &str, temp: f64, current: Level) -> Level {
+        let (warning, critical) = self.levels(id);
+        let above = |level: Option<f64>, is_current: bool| match level {
+            Some(level) if is_current => temp > level - HYSTERESIS,
+            Some(level) => temp >= level,
+            None => false,
+        };
+        if above(critical, current == Level::Critical) {
+            Level::Critical
+        } else if above(warning, current >= Level::Warning) {
+            Level::Warning
+        } else {
+            Level::Normal
+        }
+    }
+
+    /// Moves a sensor to a new level. Returns whether an event should be
+    /// sent, raising a level is rate limited per sensor unless the level is
+    /// higher than the last alert.
+    fn transition(&mut self, id: &str, level: Level, now: Instant) -> Option<Kind> {
+        let interval = Duration::from_secs(self.config.interval);
+        let state = self.states.entry(id.to_string()).or_default();
+        let previous = std::mem::replace(&mut state.level, level);
+        if level > previous {
+            let limited = state.last_alert.is_some_and(|(last_level, time)| {
+                level <= last_level && now.duration_since(time) < interval
+            });
+            if limited {
+                return None;
+            }
+            state.last_alert = Some((level, now));
+            state.active = true;
+            Some(if level == Level::Critical {
+                Kind::Critical
+            } else {
+                Kind::Warning
+            })
+        } else if level == Level::Normal && previous != Level::Normal && state.active {
+            state.active = false;
+            Some(Kind::Clear)
+        } else {
+            None
+        }
+    }
+
+    /// Whether any sensor is above its warning level
+    pub fn active(&self) -> bool {
+        self.states
+            .values()
+            .any(|state| state.level != Level::Normal)
+    }
+
+    /// Command falling back to a safe level after a fan fault
+    pub fn fallback_command(&self) -> Option<&'static str> {
+        self.config.fan_fallback.map(Fallback::command)
+    }
+
+    /// Checks the readings, the fan level and the fan speed, returns the
+    /// events to send
+    pub fn update(
+        &mut self,
+        readings: &[Reading],
+        level: Option<&str>,
+        rpm: Option<u32>,
+        now: Instant,
+    ) -> Vec<Event> {
+        let mut events = Vec::new();
+        for reading in readings {
+            let current = self
+                .states
+                .get(reading.id)
+                .map(|s| s.level)
+                .unwrap_or_default();
+            let level = self.level(reading.id, reading.temp, current);
+            if let Some(kind) = self.transition(reading.id, level, now) {
+                events.push(Event {
+                    kind,
+                    sensor: reading.id.to_string(),
+                    name: reading.name.to_string(),
+                    temp: reading.temp,
+                    rpm: None,
+                    fault: None,
+                });
+            }
+        }
+
+        // The fan is treated as critical when stopped while anything is hot
+        let hottest = readings
+            .iter()
+            .filter(|r| {
+                self.states
+                    .get(r.id)
+                    .is_some_and(|s| s.level >= Level::Warning)
+            })
+            .max_by(|a, b| a.temp.total_cmp(&b.temp));
+        let fan_level = match (rpm, hottest) {
+            (Some(0), Some(_)) => Level::Critical,
+            _ => Level::Normal,
+        };
+        if let Some(kind) = self.transition(FAN, fan_level, now) {
+            let hottest =
+                hottest.or_else(|| readings.iter().max_by(|a, b| a.temp.total_cmp(&b.temp)));
+            events.push(Event {
+                kind: if kind == Kind::Clear {
+                    Kind::FanRunning
+                } else {
+                    Kind::FanStopped
+                },
+                sensor: FAN.to_string(),
+                name: hottest.map(|r| r.name.to_string()).unwrap_or_default(),
+                temp: hottest.map(|r| r.temp).unwrap_or(f64::NAN),
+                rpm,
+                fault: None,
+            });
+        }
+
+        let hottest = readings.iter().max_by(|a, b| a.temp.total_cmp(&b.temp));
+        let fault = if self.config.fan_fault {
+            self.faults.update(level, rpm, hottest.map(|r| r.temp), now)
+        } else {
+            None
+        };
+        let fault_level = match fault {
+            Some(_) => Level::Critical,
+            None => Level::Normal,
+        };
+        if let Some(kind) = self.transition(FAN_FAULT, fault_level, now) {
+            events.push(Event {
+                kind: if kind == Kind::Clear {
+                    Kind::FanRecovered
+                } else {
+                    Kind::FanFault
+                },
+                sensor: FAN.to_string(),
+                name: hottest.map(|r| r.name.to_string()).unwrap_or_default(),
+                temp: hottest.map(|r| r.temp).unwrap_or(f64::NAN),
+                rpm,
+                fault,
+            });
+        }
+        events
+    }
+
+    /// Rings the bell, shows a notification and runs the hook for an event.
+    /// `value` is the temperature formatted in the display unit. The
+    /// notification and the hook run in the background.
+    pub fn dispatch(&self, event: &Event, value: &str, unit: Unit) {
+        if self.config.bell {
+            let mut stdout = std::io::stdout();
+            let _ = stdout.write_all(b"\x07").and_then(|_| stdout.flush());
+        }
+        if self.config.notify {
+            if let Some(address) = dbus::session_bus_address() {
+                let notification = notification(event, value, unit);
+                std::thread::spawn(move || {
+                    let _ = dbus::notify(&address, "thinkfan-tui", &notification);
+                });
+            }
+        }
+        if let Some(mut command) = self.hook_command(event, value, unit) {
+            std::thread::spawn(move || {
+                let _ = command.status();
+            });
+        }
+    }
+
+    /// Command running the hook with the event in environment variables
+    pub fn hook_command(&self, event: &Event, value: &str, unit: Unit) -> Option<Command> {
+        let hook = self.config.hook.as_ref()?;
+        let mut command = Command::new("sh");
+        command
+            .arg("-c")
+            .arg(hook)
+            .env("THINKFAN_EVENT", event.kind.name())
+            .env("THINKFAN_SENSOR", &event.sensor)
+            .env("THINKFAN_SENSOR_NAME", &event.name)
+            .env("THINKFAN_VALUE", value)
+            .env("THINKFAN_UNIT", unit.symbol(""))
+            .env("THINKFAN_CELSIUS", event.temp.to_string())
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null());
+        if let Some(rpm) = event.rpm {
+            command.env("THINKFAN_RPM", rpm.to_string());
+        }
+        if let Some(fault) = event.fault {
+            command.env("THINKFAN_FAULT", fault.name());
+        }
+        Some(command)
+    }
+}
+
+/// Summary and body describing an event, `temp` is the temperature of the
+/// event formatted with its unit
+pub fn describe(event: &Event, temp: &str) -> (String, String) {
+    match event.kind {
+        Kind::Warning => (format!("{} is hot", event.name), temp.to_string()),
+        Kind::Critical => (format!("{} is critical", event.name), temp.to_string()),
+        Kind::Clear => (
+            format!("{} is back to normal", event.name),
+            temp.to_string(),
+        ),
+        Kind::FanStopped => (
+            "Fan stopped while hot".to_string(),
+            format!("{} is at {}", event.name, temp),
+        ),
+        Kind::FanRunning => (
+            "Fan is running again".to_string(),
+            format!("{} RPM", event.rpm.unwrap_or_default()),
+        ),
+        Kind::FanFault => {
+            let rpm = event.rpm.unwrap_or_default();
+            let body = match event.fault {
+                Some(Fault::Stalled) => format!("{} RPM at a running level", rpm),
+                Some(Fault::NoResponse) => {
+                    format!("{} RPM, not rising after the level was raised", rpm)
+                }
+                Some(Fault::Flat) => format!("{} RPM while {} rose to {}", rpm, event.name, temp),
+                None => format!("{} RPM", rpm),
+            };
+            ("Probable fan fault".to_string(), body)
+        }
+        Kind::FanRecovered => (
+            "Fan responds again".to_string(),
+            format!("{} RPM", event.rpm.unwrap_or_default()),
+        ),
+    }
+}
+
+/// Desktop notification describing an event
+pub fn notification(event: &Event, value: &str, unit: Unit) -> dbus::Notification {
+    let (summary, body) = describe(event, &format!("{}{}", value, unit.symbol("°")));
+    let urgency = match event.kind {
+        Kind::Critical | Kind::FanStopped | Kind::FanFault => 2,
+        Kind::Warning => 1,
+        Kind::Clear | Kind::FanRunning | Kind::FanRecovered => 0,
+    };
+    dbus::Notification {
+        summary,
+        body,
+        urgency,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn alerts(config: &str) -> Alerts {
+        let config: AlertsConfig = toml::from_str(config).unwrap();
+        Alerts::new(config, Unit::Celsius)
+    }
+
+    fn reading(temp: f64) -> Reading<'static> {
+        Reading {
+            id: "coretemp-isa-0000/Core 0",
+            name: "Core 0",
+            temp,
+        }
+    }
+
+    fn kinds(events: &[Event]) -> Vec<Kind> {
+        events.iter().map(|e| e.kind).collect()
+    }
+
+    #[test]
+    fn levels_and_clear() {
+        let mut alerts = alerts("warning = 80\ncritical = 90\ninterval = 0");
+        let now = Instant::now();
+        let mut update = |temp| kinds(&alerts.update(&[reading(temp)], None, Some(2000), now));
+        assert_eq!(update(70.0), vec![]);
+        assert_eq!(update(80.0), vec![Kind::Warning]);
+        assert_eq!(update(85.0), vec![]);
+        assert_eq!(update(95.0), vec![Kind::Critical]);
+        // Stays critical until it drops below 88°C
+        assert_eq!(update(89.0), vec![]);
+        assert_eq!(update(87.0), vec![]);
+        assert_eq!(update(79.0), vec![]);
+        assert_eq!(update(77.0), vec![Kind::Clear]);
+        assert_eq!(update(77.0), vec![]);
+        assert!(!alerts.active());
+    }
+
+    #[test]
+    fn rate_limit() {
+        let mut alerts = alerts("warning = 80\ncritical = 90\ninterval = 60");
+        let start = Instant::now();
+        let mut update = |temp, secs| {
+            kinds(&alerts.update(
+                &[reading(temp)],
+                None,
+                None,
+                start + Duration::from_secs(secs),
+            ))
+        };
+        assert_eq!(update(81.0, 0), vec![Kind::Warning]);
+        assert_eq!(update(70.0, 1), vec![Kind::Clear]);
+        // Repeated warnings are held back, and so are their clears
+        assert_eq!(update(81.0, 2), vec![]);
+        assert_eq!(update(70.0, 3), vec![]);
+        // A higher level is let through
+        assert_eq!(update(91.0, 4), vec![Kind::Critical]);
+        assert_eq!(update(70.0, 5), vec![Kind::Clear]);
+        assert_eq!(update(81.0, 61), vec![]);
+        assert_eq!(update(70.0, 62), vec![]);
+        assert_eq!(update(81.0, 65), vec![Kind::Warning]);
+    }
+
+    #[test]
+    fn sensor_levels_and_units() {
+        let config: AlertsConfig =
+            toml::from_str("warning = 176\n[sensors.\"coretemp-isa-0000/Core 0\"]\nwarning = 194")
+                .unwrap();
+        let mut alerts = Alerts::new(config, Unit::Fahrenheit);
+        let now = Instant::now();
+        let other = Reading {
+            id: "nvme-pci-0100/Composite",
+            name: "Composite",
+            temp: 81.0,
+        };
+        let events = alerts.update(&[reading(85.0), other], None, None, now);
+        assert_eq!(kinds(&events), vec![Kind::Warning]);
+        assert_eq!(events[0].sensor, "nvme-pci-0100/Composite");
+        let events = alerts.update(&[reading(90.0), other], None, None, now);
+        assert_eq!(kinds(&events), vec![Kind::Warning]);
+        assert_eq!(events[0].name, "Core 0");
+        assert!(alerts.active());
+    }
+
+    #[test]
+    fn fan_stopped() {
+        let mut alerts = alerts("warning = 80\ninterval = 0");
+        let now = Instant::now();
+        assert_eq!(
+            kinds(&alerts.update(&[reading(70.0)], None, Some(0), now)),
+            vec![]
+        );
+        let events = alerts.update(&[reading(85.0)], None, Some(0), now);
+        assert_eq!(kinds(&events), vec![Kind::Warning, Kind::FanStopped]);
+        assert_eq!(events[1].name, "Core 0");
+        assert_eq!(events[1].rpm, Some(0));
+        let events = alerts.update(&[reading(85.0)], None, Some(3000), now);
+        assert_eq!(kinds(&events), vec![Kind::FanRunning]);
+    }
+
+    #[test]
+    fn disabled_by_default() {
+        let mut alerts = Alerts::default();
+        let events = alerts.update(&[reading(120.0)], None, Some(0), Instant::now());
+        assert!(events.is_empty());
+    }
+
+    #[test]
+    fn fan_fault() {
+        let mut alerts =
+            alerts("fan_fault = true\nfan_response = 5\nfan_fallback = \"full-speed\"");
+        let start = Instant::now();
+        let mut update = |level, rpm, secs| {
+            alerts.update(
+                &[reading(60.0)],
+                Some(level),
+                Some(rpm),
+                start + Duration::from_secs(secs),
+            )
+        };
+        assert_eq!(kinds(&update("7", 0, 0)), vec![]);
+        let events = update("7", 0, 5);
+        assert_eq!(kinds(&events), vec![Kind::FanFault]);
+        assert_eq!(events[0].fault, Some(Fault::Stalled));
+        let (summary, body) = describe(&events[0], "60°C");
+        assert_eq!(summary, "Probable fan fault");
+        assert_eq!(body, "0 RPM at a running level");
+        assert_eq!(kinds(&update("full-speed", 0, 6)), vec![]);
+        assert_eq!(
+            kinds(&update("full-speed", 5000, 7)),
+            vec![Kind::FanRecovered]
+        );
+        assert_eq!(alerts.fallback_command(), Some("level full-speed"));
+
+        let mut disabled = Alerts::default();
+        let events = disabled.update(&[reading(60.0)], Some("7"), Some(0), start);
+        assert!(events.is_empty());
+    }
+
+    #[test]
+    fn hook_environment() {
+        let dir = std::env::temp_dir().join(format!("thinkfan-tui-hook-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let out = dir.join("out");
+        let alerts = alerts(&format!(
+            "hook = 'echo \"$THINKFAN_EVENT $THINKFAN_SENSOR_NAME $THINKFAN_VALUE$THINKFAN_UNIT\" > {}'",
+            out.display()
+        ));
+        let event = Event {
+            kind: Kind::Critical,
+            sensor: "coretemp-isa-0000/Core 0".to_string(),
+            name: "Core 0".to_string(),
+            temp: 95.0,
+            rpm: None,
+            fault: None,
+        };
+        let status = alerts
+            .hook_command(&event, "203", Unit::Fahrenheit)
+            .unwrap()
+            .status()
+            .unwrap();
+        assert!(status.success());
+        assert_eq!(
+            std::fs::read_to_string(&out).unwrap(),
+            "critical Core 0 203F\n"
+        );
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn notify_over_bus() {
+        let (address, notifications, path) = dbus::tests::fake_bus("alerts");
+        let event = Event {
+            kind: Kind::FanStopped,
+            sensor: FAN.to_string(),
+            name: "CPU".to_string(),
+            temp: 85.0,
+            rpm: Some(0),
+            fault: None,
+        };
+        let notification = notification(&event, "85", Unit::Celsius);
+        dbus::notify(&address, "thinkfan-tui", &notification).unwrap();
+        let received = notifications.recv().unwrap();
+        assert_eq!(received.summary, "Fan stopped while hot");
+        assert_eq!(received.body, "CPU is at 85°C");
+        assert_eq!(received.urgency, 2);
+        std::fs::remove_file(path).unwrap();
+    }
+}
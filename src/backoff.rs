@@ -0,0 +1,72 @@
+use std::time::{Duration, Instant};
+
+const FIRST_DELAY: Duration = Duration::from_secs(1);
+const MAX_DELAY: Duration = Duration::from_secs(30);
+
+/// Delays the retries of a failing read, doubling the delay after every
+/// failure up to 30 seconds
+#[derive(Debug, Default)]
+pub struct Backoff {
+    failures: u32,
+    retry_at: Option<Instant>,
+    /// Time of the first failure since the last success
+    since: Option<Instant>,
+}
+
+impl Backoff {
+    /// Whether the read should be tried now
+    pub fn ready(&self, now: Instant) -> bool {
+        self.retry_at.is_none_or(|retry_at| now >= retry_at)
+    }
+
+    /// Records a failure, returns the delay until the next try
+    pub fn failed(&mut self, now: Instant) -> Duration {
+        let delay = (FIRST_DELAY * 2u32.pow(self.failures.min(5))).min(MAX_DELAY);
+        self.failures += 1;
+        self.since.get_or_insert(now);
+        self.retry_at = Some(now + delay);
+        delay
+    }
+
+    /// Records a success, returns whether the read was failing before
+    pub fn succeeded(&mut self) -> bool {
+        let failing = self.failures > 0;
+        *self = Self::default();
+        failing
+    }
+
+    /// Time of the first failure, if the read is failing
+    pub fn failing_since(&self) -> Option<Instant> {
+        self.since
+    }
+
+    /// Time left until the next try, if the read is failing
+    pub fn retry_in(&self, now: Instant) -> Option<Duration> {
+        self.retry_at
+            .map(|retry_at| retry_at.saturating_duration_since(now))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn delays() {
+        let now = Instant::now();
+        let mut backoff = Backoff::default();
+        assert!(backoff.ready(now));
+        assert_eq!(backoff.failed(now), Duration::from_secs(1));
+        assert!(!backoff.ready(now));
+        assert!(backoff.ready(now + Duration::from_secs(1)));
+        let delays: Vec<u64> = (0..6).map(|_| backoff.failed(now).as_secs()).collect();
+        assert_eq!(delays, [2, 4, 8, 16, 30, 30]);
+        assert_eq!(backoff.failing_since(), Some(now));
+        assert_eq!(backoff.retry_in(now), Some(Duration::from_secs(30)));
+
+        assert!(backoff.succeeded());
+        assert!(!backoff.succeeded());
+        assert!(backoff.ready(now));
+        assert_eq!(backoff.failing_since(), None);
+    }
+}
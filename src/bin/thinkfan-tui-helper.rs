@@ -0,0 +1,168 @@
+//! Privileged helper of thinkfan-tui, started with pkexec. Says `ready`, then
+//! reads one fan command per line from stdin, writes the valid ones to
+//! `/proc/acpi/ibm/fan` and answers each line with `ok` or `error <reason>`.
+//! Lines such as `pwm2_enable 1` or `pwm2 109` set a single fan through the
+//! pwm files of the thinkpad hwmon device. Nothing but these files is ever
+//! written.
+
+use std::io::{self, BufRead, Write};
+use std::path::PathBuf;
+
+static PATH_FAN: &str = "/proc/acpi/ibm/fan";
+static PATH_HWMON: &str = "/sys/class/hwmon";
+
+/// Longest command accepted, longer lines are refused without parsing
+const MAX_LENGTH: usize = 32;
+
+/// Whether a command is one thinkpad_acpi documents for the fan file
+fn is_valid(command: &str) -> bool {
+    if command.len() > MAX_LENGTH {
+        return false;
+    }
+    match command.split_once(' ') {
+        Some(("level", level)) => matches!(
+            level,
+            "0" | "1" | "2" | "3" | "4" | "5" | "6" | "7" | "auto" | "full-speed" | "disengaged"
+        ),
+        Some(("watchdog", timeout)) => timeout.parse::<u8>().is_ok_and(|t| t <= 120),
+        _ => command == "enable" || command == "disable",
+    }
+}
+
+/// File name and value of a pwm command, e.g. ("pwm2", "109"). Only the
+/// `pwm<n>` and `pwm<n>_enable` files of the first nine fans are accepted.
+fn pwm_command(command: &str) -> Option<(&str, &str)> {
+    if command.len() > MAX_LENGTH {
+        return None;
+    }
+    let (file, value) = command.split_once(' ')?;
+    let index = file.strip_prefix("pwm")?;
+    let (index, value_max) = match index.strip_suffix("_enable") {
+        Some(index) => (index, 2),
+        None => (index, 255),
+    };
+    let valid = matches!(index, "1" | "2" | "3" | "4" | "5" | "6" | "7" | "8" | "9")
+        && value.bytes().all(|b| b.is_ascii_digit())
+        && value.parse::<u8>().is_ok_and(|v| v <= value_max);
+    valid.then_some((file, value))
+}
+
+/// Directory of the hwmon device of thinkpad_acpi, e.g.
+/// `/sys/class/hwmon/hwmon3`
+fn thinkpad_hwmon() -> io::Result<PathBuf> {
+    for entry in std::fs::read_dir(PATH_HWMON)? {
+        let path = entry?.path();
+        if std::fs::read_to_string(path.join("name")).is_ok_and(|n| n.trim() == "thinkpad") {
+            return Ok(path);
+        }
+    }
+    Err(io::Error::new(
+        io::ErrorKind::NotFound,
+        "no thinkpad hwmon device",
+    ))
+}
+
+/// Answers the commands of `input` until it is closed
+fn serve(
+    input: impl BufRead,
+    mut output: impl Write,
+    mut write_fan: impl FnMut(&str) -> io::Result<()>,
+) -> io::Result<()> {
+    writeln!(output, "ready")?;
+    output.flush()?;
+    for line in input.lines() {
+        let line = line?;
+        let command = line.trim();
+        if !is_valid(command) && pwm_command(command).is_none() {
+            writeln!(output, "error invalid command")?;
+        } else if let Err(e) = write_fan(command) {
+            writeln!(output, "error {}", e)?;
+        } else {
+            writeln!(output, "ok")?;
+        }
+        output.flush()?;
+    }
+    Ok(())
+}
+
+fn main() -> io::Result<()> {
+    if std::env::args().len() > 1 {
+        eprintln!("thinkfan-tui-helper takes no arguments, it is started by thinkfan-tui");
+        std::process::exit(2);
+    }
+    serve(
+        io::stdin().lock(),
+        io::stdout().lock(),
+        |command| match pwm_command(command) {
+            Some((file, value)) => std::fs::write(thinkpad_hwmon()?.join(file), value),
+            None => std::fs::write(PATH_FAN, command),
+        },
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn validation() {
+        assert!(is_valid("level 0"));
+        assert!(is_valid("level disengaged"));
+        assert!(is_valid("watchdog 120"));
+        assert!(is_valid("enable"));
+        assert!(!is_valid("level 8"));
+        assert!(!is_valid("level auto\nlevel 0"));
+        assert!(!is_valid("watchdog 121"));
+        assert!(!is_valid("watchdog -1"));
+        assert!(!is_valid("../../etc/passwd"));
+        assert!(!is_valid(""));
+    }
+
+    #[test]
+    fn pwm_validation() {
+        assert_eq!(pwm_command("pwm1 109"), Some(("pwm1", "109")));
+        assert_eq!(pwm_command("pwm2_enable 2"), Some(("pwm2_enable", "2")));
+        assert_eq!(pwm_command("pwm2 255"), Some(("pwm2", "255")));
+        assert_eq!(pwm_command("pwm2 256"), None);
+        assert_eq!(pwm_command("pwm2 +1"), None);
+        assert_eq!(pwm_command("pwm2_enable 3"), None);
+        assert_eq!(pwm_command("pwm0 1"), None);
+        assert_eq!(pwm_command("pwm10 1"), None);
+        assert_eq!(pwm_command("pwm0_enable 1"), None);
+        assert_eq!(pwm_command("pwm1_auto_point1_pwm 1"), None);
+        assert_eq!(pwm_command("pwm/../../name 1"), None);
+        assert_eq!(pwm_command("pwm1"), None);
+        assert_eq!(pwm_command("level 3"), None);
+    }
+
+    #[test]
+    fn answers() {
+        let mut written = Vec::new();
+        let mut output = Vec::new();
+        serve(
+            "level 3\nlevel 9\n  level auto  \nwatchdog 30\npwm2 109\npwm3 300\n".as_bytes(),
+            &mut output,
+            |command| {
+                written.push(command.to_string());
+                if command.starts_with("watchdog") {
+                    Err(io::Error::new(
+                        io::ErrorKind::InvalidInput,
+                        "Invalid argument",
+                    ))
+                } else {
+                    Ok(())
+                }
+            },
+        )
+        .unwrap();
+        assert_eq!(
+            written,
+            vec!["level 3", "level auto", "watchdog 30", "pwm2 109"]
+        );
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "ready\nok\nerror invalid command\nok\nerror Invalid argument\nok\n\
+             error invalid command\n"
+        );
+    }
+}
@@ -0,0 +1,68 @@
+pub static USAGE: &str = "\
+Usage: thinkfan-tui [OPTIONS] [COMMAND]
+
+Commands:
+  doctor  Check the driver, permissions and sensors and print how to fix
+          problems
+
+Options:
+      --ascii      Draw with ASCII characters only
+      --unicode    Draw with Unicode characters, even if the terminal seems
+                   not to support them
+  -r, --read-only  Only show temperatures and fan status, never ask for
+                   permission to control the fan
+  -h, --help       Print help
+  -V, --version    Print version";
+
+/// Command line options
+#[derive(Debug, Default, PartialEq)]
+pub struct Args {
+    /// Draw with ASCII characters only, detected from the terminal if not
+    /// given
+    pub ascii: Option<bool>,
+    pub read_only: bool,
+    /// Run the `doctor` command instead of the TUI
+    pub doctor: bool,
+    pub help: bool,
+    pub version: bool,
+}
+
+/// Parses the arguments, without the program name
+pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Args, String> {
+    let mut parsed = Args::default();
+    for arg in args {
+        match arg.as_str() {
+            "--ascii" => parsed.ascii = Some(true),
+            "--unicode" => parsed.ascii = Some(false),
+            "-r" | "--read-only" => parsed.read_only = true,
+            "doctor" => parsed.doctor = true,
+            "-h" | "--help" => parsed.help = true,
+            "-V" | "--version" => parsed.version = true,
+            _ => return Err(format!("unexpected argument '{}'", arg)),
+        }
+    }
+    Ok(parsed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse_str(args: &[&str]) -> Result<Args, String> {
+        parse(args.iter().map(|arg| arg.to_string()))
+    }
+
+    #[test]
+    fn parse_args() {
+        assert_eq!(parse_str(&[]), Ok(Args::default()));
+        assert_eq!(parse_str(&["--ascii"]).unwrap().ascii, Some(true));
+        assert_eq!(
+            parse_str(&["--ascii", "--unicode"]).unwrap().ascii,
+            Some(false)
+        );
+        assert!(parse_str(&["-h"]).unwrap().help);
+        assert!(parse_str(&["--read-only"]).unwrap().read_only);
+        assert!(parse_str(&["doctor"]).unwrap().doctor);
+        assert!(parse_str(&["--verbose"]).is_err());
+    }
+}
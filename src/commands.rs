@@ -0,0 +1,124 @@
+/// Commands thinkpad_acpi accepts in `/proc/acpi/ibm/fan`, from the
+/// `commands:` lines it lists when fan control is enabled, e.g.
+///
+/// ```text
+/// commands:       level <level> (<level> is 0-7, auto, disengaged, full-speed)
+/// commands:       enable, disable
+/// commands:       watchdog <timeout> (<timeout> is 0 (off), 1-120 (seconds))
+/// ```
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Commands {
+    /// Accepted levels, e.g. "3" or "disengaged"
+    pub levels: Vec<String>,
+    pub enable: bool,
+    pub disable: bool,
+    /// Longest watchdog timeout in seconds, if there is a watchdog
+    pub watchdog: Option<u32>,
+}
+
+impl Commands {
+    /// Parses the lines of `/proc/acpi/ibm/fan`, `None` if there are no
+    /// `commands:` lines
+    pub fn parse<'a>(lines: impl IntoIterator<Item = &'a str>) -> Option<Self> {
+        let mut commands = Self::default();
+        let mut found = false;
+        for line in lines {
+            let Some(line) = line.strip_prefix("commands:") else {
+                continue;
+            };
+            found = true;
+            let line = line.trim();
+            if let Some(rest) = line.strip_prefix("level ") {
+                for value in choices(rest) {
+                    match value.split_once('-').and_then(|(from, to)| {
+                        Some((from.parse::<u8>().ok()?, to.parse::<u8>().ok()?))
+                    }) {
+                        Some((from, to)) => {
+                            commands.levels.extend((from..=to).map(|l| l.to_string()))
+                        }
+                        None => commands.levels.push(value.to_string()),
+                    }
+                }
+            } else if let Some(rest) = line.strip_prefix("watchdog ") {
+                commands.watchdog = choices(rest)
+                    .filter_map(|value| value.split_whitespace().next()?.split_once('-'))
+                    .find_map(|(_, to)| to.parse().ok());
+            } else {
+                for command in line.split(',').map(str::trim) {
+                    match command {
+                        "enable" => commands.enable = true,
+                        "disable" => commands.disable = true,
+                        _ => {}
+                    }
+                }
+            }
+        }
+        found.then_some(commands)
+    }
+
+    /// Whether a command such as "level disengaged" or "watchdog 120" is
+    /// accepted
+    pub fn supports(&self, command: &str) -> bool {
+        match command.split_once(' ') {
+            Some(("level", level)) => self.levels.iter().any(|l| l == level),
+            Some(("watchdog", timeout)) => timeout
+                .parse::<u32>()
+                .is_ok_and(|t| self.watchdog.is_some_and(|max| t <= max)),
+            _ => match command {
+                "enable" => self.enable,
+                "disable" => self.disable,
+                _ => false,
+            },
+        }
+    }
+}
+
+/// Values listed in parentheses after "is", e.g. "0-7" and "auto" of
+/// "<level> (<level> is 0-7, auto)"
+fn choices(text: &str) -> impl Iterator<Item = &str> {
+    let list = text
+        .split_once(" is ")
+        .map(|(_, list)| list.strip_suffix(')').unwrap_or(list))
+        .unwrap_or_default();
+    list.split(", ").map(str::trim).filter(|v| !v.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_commands() {
+        let content = "status:\t\tenabled\n\
+            speed:\t\t2000\n\
+            level:\t\tauto\n\
+            commands:\tlevel <level> (<level> is 0-7, auto, disengaged, full-speed)\n\
+            commands:\tenable, disable\n\
+            commands:\twatchdog <timeout> (<timeout> is 0 (off), 1-120 (seconds))\n";
+        let commands = Commands::parse(content.lines()).unwrap();
+        assert_eq!(commands.levels.len(), 11);
+        assert_eq!(commands.levels[8], "auto");
+        assert!(commands.enable && commands.disable);
+        assert_eq!(commands.watchdog, Some(120));
+
+        assert!(commands.supports("level 7"));
+        assert!(commands.supports("level disengaged"));
+        assert!(!commands.supports("level 8"));
+        assert!(commands.supports("disable"));
+        assert!(commands.supports("watchdog 0"));
+        assert!(!commands.supports("watchdog 300"));
+    }
+
+    #[test]
+    fn limited_commands() {
+        let content = "status:\t\tenabled\ncommands:\tlevel <level> (<level> is 0-7, auto)\n";
+        let commands = Commands::parse(content.lines()).unwrap();
+        assert!(commands.supports("level auto"));
+        assert!(!commands.supports("level disengaged"));
+        assert!(!commands.supports("enable"));
+        assert!(!commands.supports("watchdog 10"));
+
+        // Fan control disabled in the driver
+        assert_eq!(Commands::parse("status:\t\tenabled\n".lines()), None);
+    }
+}
@@ -0,0 +1,129 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use serde::Deserialize;
+
+use crate::alerts::AlertsConfig;
+use crate::keymap::KeysConfig;
+use crate::safety::SafetyConfig;
+use crate::sampler::PollingConfig;
+use crate::theme::ThemeConfig;
+use crate::units::Unit;
+
+/// Settings read from `~/.config/thinkfan-tui/config.toml`. Every setting is
+/// optional.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// Name of a built-in or user theme
+    pub theme: Option<String>,
+    /// Draw with ASCII characters only, detected from the terminal if not
+    /// given
+    pub ascii: Option<bool>,
+    pub unit: Unit,
+    /// Number of decimals of temperatures
+    pub precision: Option<usize>,
+    /// File the event log is appended to, `~/` is expanded
+    pub log_file: Option<PathBuf>,
+    pub keys: KeysConfig,
+    pub alerts: AlertsConfig,
+    pub safety: SafetyConfig,
+    pub polling: PollingConfig,
+    pub themes: HashMap<String, ThemeConfig>,
+}
+
+/// Location of the config file, following the XDG base directory spec
+pub fn path() -> Option<PathBuf> {
+    let config_dir = match std::env::var_os("XDG_CONFIG_HOME") {
+        Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => PathBuf::from(std::env::var_os("HOME")?).join(".config"),
+    };
+    Some(config_dir.join("thinkfan-tui").join("config.toml"))
+}
+
+/// Replaces a leading `~/` with the home directory
+pub fn expand_home(path: &Path) -> PathBuf {
+    match (path.strip_prefix("~"), std::env::var_os("HOME")) {
+        (Ok(rest), Some(home)) => PathBuf::from(home).join(rest),
+        _ => path.to_path_buf(),
+    }
+}
+
+pub fn parse(content: &str) -> Result<Config, String> {
+    toml::from_str(content).map_err(|e| e.to_string())
+}
+
+/// Loads the config file, a missing file gives the default config
+pub fn load() -> Result<Config, String> {
+    let Some(path) = path() else {
+        return Ok(Config::default());
+    };
+    match std::fs::read_to_string(&path) {
+        Ok(content) => parse(&content).map_err(|e| format!("{}: {}", path.display(), e)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Config::default()),
+        Err(e) => Err(format!("{}: {}", path.display(), e)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn empty_config() {
+        let config = parse("").unwrap();
+        assert!(config.keys.bindings.is_empty());
+        assert!(config.theme.is_none());
+    }
+
+    #[test]
+    fn home_directory() {
+        let home = PathBuf::from(std::env::var_os("HOME").unwrap());
+        assert_eq!(
+            expand_home(Path::new("~/log/events.log")),
+            home.join("log/events.log")
+        );
+        assert_eq!(
+            expand_home(Path::new("/var/log/x")),
+            PathBuf::from("/var/log/x")
+        );
+        assert_eq!(expand_home(Path::new("~x/y")), PathBuf::from("~x/y"));
+    }
+
+    #[test]
+    fn user_theme() {
+        let config =
+            parse("theme = \"mine\"\n\n[themes.mine]\nbase = \"light\"\ntext = \"black\"\n")
+                .unwrap();
+        assert_eq!(config.theme.as_deref(), Some("mine"));
+        assert_eq!(config.themes["mine"].base.as_deref(), Some("light"));
+    }
+
+    #[test]
+    fn alerts_section() {
+        let config = parse(
+            r#"
+            [alerts]
+            warning = 80
+            critical = 95
+            hook = "logger -t thinkfan-tui \"$THINKFAN_EVENT\""
+
+            [alerts.sensors."nvme-pci-0100/Composite"]
+            warning = 70
+            "#,
+        )
+        .unwrap();
+        assert_eq!(config.alerts.warning, Some(80.0));
+        assert!(config.alerts.bell);
+        assert_eq!(
+            config.alerts.sensors["nvme-pci-0100/Composite"].warning,
+            Some(70.0)
+        );
+    }
+
+    #[test]
+    fn unknown_section() {
+        assert!(parse("[colours]\n").is_err());
+        assert!(parse("[alerts]\nwarn = 80\n").is_err());
+    }
+}
@@ -0,0 +1,107 @@
+use std::path::Path;
+use std::process::Command;
+
+/// Fan daemons that overwrite the levels written by thinkfan-tui
+pub static FAN_DAEMONS: &[&str] = &["thinkfan", "fancontrol"];
+
+/// A fan daemon found running
+#[derive(Debug, Clone, PartialEq)]
+pub struct Daemon {
+    pub name: String,
+    /// Active systemd unit running it, e.g. "thinkfan.service"
+    pub unit: Option<String>,
+}
+
+/// Finds the running fan daemons by process name and by the state of their
+/// systemd units
+pub fn detect() -> Vec<Daemon> {
+    let processes = running_processes(Path::new("/proc"), FAN_DAEMONS);
+    find(&processes, |unit| unit_active(unit) == Some(true))
+}
+
+fn find(processes: &[String], unit_active: impl Fn(&str) -> bool) -> Vec<Daemon> {
+    FAN_DAEMONS
+        .iter()
+        .filter_map(|name| {
+            let unit = format!("{}.service", name);
+            let active = unit_active(&unit);
+            (active || processes.iter().any(|p| p == name)).then(|| Daemon {
+                name: name.to_string(),
+                unit: active.then_some(unit),
+            })
+        })
+        .collect()
+}
+
+/// Whether a systemd unit is active, `None` without systemd
+fn unit_active(unit: &str) -> Option<bool> {
+    Command::new("systemctl")
+        .args(["is-active", "--quiet", unit])
+        .status()
+        .ok()
+        .map(|status| status.success())
+}
+
+/// Command stopping or starting systemd units, e.g. `systemctl stop
+/// thinkfan.service`, which is authenticated by polkit
+pub fn systemctl(verb: &str, units: &[String]) -> Command {
+    let mut command = Command::new("systemctl");
+    command.arg(verb).args(units);
+    command
+}
+
+/// Names of the given processes that are running, from `/proc/<pid>/comm`
+pub fn running_processes(proc_root: &Path, names: &[&str]) -> Vec<String> {
+    let Ok(entries) = std::fs::read_dir(proc_root) else {
+        return Vec::new();
+    };
+    let mut running: Vec<String> = entries
+        .filter_map(|entry| std::fs::read_to_string(entry.ok()?.path().join("comm")).ok())
+        .map(|comm| comm.trim().to_string())
+        .filter(|comm| names.contains(&comm.as_str()))
+        .collect();
+    running.sort();
+    running.dedup();
+    running
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn processes() {
+        let root = std::env::temp_dir().join(format!("thinkfan-tui-proc-{}", std::process::id()));
+        for (pid, comm) in [("1", "systemd"), ("42", "thinkfan"), ("43", "thinkfan")] {
+            std::fs::create_dir_all(root.join(pid)).unwrap();
+            std::fs::write(root.join(pid).join("comm"), format!("{}\n", comm)).unwrap();
+        }
+        assert_eq!(running_processes(&root, FAN_DAEMONS), vec!["thinkfan"]);
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn daemons() {
+        assert_eq!(find(&[], |_| false), vec![]);
+        // Started by hand
+        assert_eq!(
+            find(&["fancontrol".to_string()], |_| false),
+            vec![Daemon {
+                name: "fancontrol".into(),
+                unit: None
+            }]
+        );
+        assert_eq!(
+            find(&["thinkfan".to_string()], |unit| unit == "thinkfan.service"),
+            vec![Daemon {
+                name: "thinkfan".into(),
+                unit: Some("thinkfan.service".into())
+            }]
+        );
+
+        let command = systemctl("stop", &["thinkfan.service".to_string()]);
+        let args: Vec<_> = command.get_args().collect();
+        assert_eq!(command.get_program(), "systemctl");
+        assert_eq!(args, ["stop", "thinkfan.service"]);
+    }
+}
@@ -0,0 +1,567 @@
+//! Minimal D-Bus client, enough to call methods on the session bus without
+//! linking libdbus. Only what desktop notifications need is supported.
+
+use std::io::{self, Read, Write};
+use std::os::unix::fs::MetadataExt;
+use std::os::unix::net::UnixStream;
+use std::time::Duration;
+
+const METHOD_CALL: u8 = 1;
+const METHOD_RETURN: u8 = 2;
+const ERROR: u8 = 3;
+
+const FIELD_PATH: u8 = 1;
+const FIELD_INTERFACE: u8 = 2;
+const FIELD_MEMBER: u8 = 3;
+const FIELD_ERROR_NAME: u8 = 4;
+const FIELD_REPLY_SERIAL: u8 = 5;
+const FIELD_DESTINATION: u8 = 6;
+const FIELD_SIGNATURE: u8 = 8;
+
+const TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Address of the session bus from `DBUS_SESSION_BUS_ADDRESS`
+pub fn session_bus_address() -> Option<String> {
+    std::env::var("DBUS_SESSION_BUS_ADDRESS")
+        .ok()
+        .filter(|address| !address.is_empty())
+}
+
+fn invalid_data(message: impl Into<String>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.into())
+}
+
+/// Connects to the first address in the list that is a Unix socket, e.g.
+/// `unix:path=/run/user/1000/bus` or `unix:abstract=/tmp/dbus-x,guid=...`
+fn connect_socket(address: &str) -> io::Result<UnixStream> {
+    for address in address.split(';') {
+        let Some(params) = address.strip_prefix("unix:") else {
+            continue;
+        };
+        for param in params.split(',') {
+            match param.split_once('=') {
+                Some(("path", path)) => return UnixStream::connect(path),
+                Some(("abstract", name)) => {
+                    use std::os::linux::net::SocketAddrExt;
+                    let addr = std::os::unix::net::SocketAddr::from_abstract_name(name)?;
+                    return UnixStream::connect_addr(&addr);
+                }
+                _ => {}
+            }
+        }
+    }
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        format!("no supported transport in bus address '{}'", address),
+    ))
+}
+
+/// Builds a message body, aligning values as required by the wire format
+#[derive(Debug, Default)]
+pub struct Writer {
+    buf: Vec<u8>,
+}
+
+impl Writer {
+    fn align(&mut self, alignment: usize) {
+        while !self.buf.len().is_multiple_of(alignment) {
+            self.buf.push(0);
+        }
+    }
+
+    pub fn byte(&mut self, value: u8) {
+        self.buf.push(value);
+    }
+
+    pub fn u32(&mut self, value: u32) {
+        self.align(4);
+        self.buf.extend_from_slice(&value.to_le_bytes());
+    }
+
+    pub fn i32(&mut self, value: i32) {
+        self.align(4);
+        self.buf.extend_from_slice(&value.to_le_bytes());
+    }
+
+    pub fn string(&mut self, value: &str) {
+        self.u32(value.len() as u32);
+        self.buf.extend_from_slice(value.as_bytes());
+        self.buf.push(0);
+    }
+
+    pub fn signature(&mut self, value: &str) {
+        self.buf.push(value.len() as u8);
+        self.buf.extend_from_slice(value.as_bytes());
+        self.buf.push(0);
+    }
+
+    /// Writes an array, `element_alignment` is 8 for structs and dict
+    /// entries
+    pub fn array(&mut self, element_alignment: usize, elements: impl FnOnce(&mut Self)) {
+        self.u32(0);
+        let length_at = self.buf.len() - 4;
+        self.align(element_alignment);
+        let start = self.buf.len();
+        elements(self);
+        let length = (self.buf.len() - start) as u32;
+        self.buf[length_at..length_at + 4].copy_from_slice(&length.to_le_bytes());
+    }
+
+    /// Starts a struct or dict entry
+    pub fn start_struct(&mut self) {
+        self.align(8);
+    }
+
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.buf
+    }
+}
+
+/// Reads values of a message, in the byte order of the sender
+#[derive(Debug)]
+pub struct Reader<'a> {
+    buf: &'a [u8],
+    pos: usize,
+    big_endian: bool,
+}
+
+impl<'a> Reader<'a> {
+    pub fn new(buf: &'a [u8], big_endian: bool) -> Self {
+        Self {
+            buf,
+            pos: 0,
+            big_endian,
+        }
+    }
+
+    fn align(&mut self, alignment: usize) {
+        self.pos = self.pos.div_ceil(alignment) * alignment;
+    }
+
+    fn take(&mut self, len: usize) -> io::Result<&'a [u8]> {
+        let bytes = self
+            .buf
+            .get(self.pos..self.pos + len)
+            .ok_or_else(|| invalid_data("truncated D-Bus message"))?;
+        self.pos += len;
+        Ok(bytes)
+    }
+
+    pub fn byte(&mut self) -> io::Result<u8> {
+        Ok(self.take(1)?[0])
+    }
+
+    pub fn u32(&mut self) -> io::Result<u32> {
+        self.align(4);
+        let bytes: [u8; 4] = self.take(4)?.try_into().unwrap();
+        Ok(if self.big_endian {
+            u32::from_be_bytes(bytes)
+        } else {
+            u32::from_le_bytes(bytes)
+        })
+    }
+
+    #[cfg(test)]
+    pub fn i32(&mut self) -> io::Result<i32> {
+        self.u32().map(|value| value as i32)
+    }
+
+    pub fn string(&mut self) -> io::Result<String> {
+        let len = self.u32()? as usize;
+        let bytes = self.take(len + 1)?;
+        String::from_utf8(bytes[..len].to_vec()).map_err(|_| invalid_data("invalid UTF-8"))
+    }
+
+    pub fn signature(&mut self) -> io::Result<String> {
+        let len = self.byte()? as usize;
+        let bytes = self.take(len + 1)?;
+        String::from_utf8(bytes[..len].to_vec()).map_err(|_| invalid_data("invalid UTF-8"))
+    }
+
+    /// Reads the length of an array and skips the padding before the first
+    /// element. Returns the position after the last element.
+    #[cfg(test)]
+    pub fn array(&mut self, element_alignment: usize) -> io::Result<usize> {
+        let len = self.u32()? as usize;
+        self.align(element_alignment);
+        Ok(self.pos + len)
+    }
+
+    pub fn start_struct(&mut self) {
+        self.align(8);
+    }
+
+    #[cfg(test)]
+    pub fn position(&self) -> usize {
+        self.pos
+    }
+
+    /// Reads a variant holding a basic type, other types are skipped
+    fn variant(&mut self) -> io::Result<Value> {
+        Ok(match self.signature()?.as_str() {
+            "s" | "o" => Value::String(self.string()?),
+            "g" => Value::String(self.signature()?),
+            "u" => Value::U32(self.u32()?),
+            "y" => Value::U32(self.byte()? as u32),
+            _ => Value::Other,
+        })
+    }
+}
+
+enum Value {
+    String(String),
+    U32(u32),
+    Other,
+}
+
+/// A message read from or written to the bus
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Message {
+    pub message_type: u8,
+    pub serial: u32,
+    pub path: Option<String>,
+    pub interface: Option<String>,
+    pub member: Option<String>,
+    pub error_name: Option<String>,
+    pub reply_serial: Option<u32>,
+    pub destination: Option<String>,
+    pub signature: String,
+    pub body: Vec<u8>,
+    pub big_endian: bool,
+}
+
+impl Message {
+    pub fn method_call(destination: &str, path: &str, interface: &str, member: &str) -> Self {
+        Self {
+            message_type: METHOD_CALL,
+            path: Some(path.to_string()),
+            interface: Some(interface.to_string()),
+            member: Some(member.to_string()),
+            destination: Some(destination.to_string()),
+            ..Default::default()
+        }
+    }
+
+    #[cfg(test)]
+    pub fn method_return(call: &Message, signature: &str, body: Vec<u8>) -> Self {
+        Self {
+            message_type: METHOD_RETURN,
+            reply_serial: Some(call.serial),
+            signature: signature.to_string(),
+            body,
+            ..Default::default()
+        }
+    }
+
+    pub fn body_reader(&self) -> Reader<'_> {
+        Reader::new(&self.body, self.big_endian)
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut w = Writer::default();
+        w.byte(b'l');
+        w.byte(self.message_type);
+        w.byte(0);
+        w.byte(1);
+        w.u32(self.body.len() as u32);
+        w.u32(self.serial);
+        w.array(8, |w| {
+            let string_fields = [
+                (FIELD_PATH, "o", &self.path),
+                (FIELD_INTERFACE, "s", &self.interface),
+                (FIELD_MEMBER, "s", &self.member),
+                (FIELD_ERROR_NAME, "s", &self.error_name),
+                (FIELD_DESTINATION, "s", &self.destination),
+            ];
+            for (code, signature, value) in string_fields {
+                if let Some(value) = value {
+                    w.start_struct();
+                    w.byte(code);
+                    w.signature(signature);
+                    w.string(value);
+                }
+            }
+            if let Some(reply_serial) = self.reply_serial {
+                w.start_struct();
+                w.byte(FIELD_REPLY_SERIAL);
+                w.signature("u");
+                w.u32(reply_serial);
+            }
+            if !self.signature.is_empty() {
+                w.start_struct();
+                w.byte(FIELD_SIGNATURE);
+                w.signature("g");
+                w.signature(&self.signature);
+            }
+        });
+        w.start_struct();
+        let mut bytes = w.into_bytes();
+        bytes.extend_from_slice(&self.body);
+        bytes
+    }
+
+    /// Reads the next message from a stream
+    pub fn read_from(stream: &mut impl Read) -> io::Result<Self> {
+        let mut fixed = [0; 16];
+        stream.read_exact(&mut fixed)?;
+        let big_endian = match fixed[0] {
+            b'l' => false,
+            b'B' => true,
+            _ => return Err(invalid_data("invalid D-Bus byte order")),
+        };
+        let mut r = Reader::new(&fixed, big_endian);
+        r.pos = 4;
+        let body_len = r.u32()? as usize;
+        let serial = r.u32()?;
+        let fields_len = r.u32()? as usize;
+
+        // The fields are padded to a multiple of 8 bytes
+        let mut rest = vec![0; (16 + fields_len).div_ceil(8) * 8 - 16 + body_len];
+        stream.read_exact(&mut rest)?;
+        let mut header = fixed.to_vec();
+        header.extend_from_slice(&rest);
+
+        let mut message = Message {
+            message_type: fixed[1],
+            serial,
+            big_endian,
+            ..Default::default()
+        };
+        let mut r = Reader::new(&header, big_endian);
+        r.pos = 16;
+        while r.pos < 16 + fields_len {
+            r.start_struct();
+            let code = r.byte()?;
+            match (code, r.variant()?) {
+                (FIELD_PATH, Value::String(value)) => message.path = Some(value),
+                (FIELD_INTERFACE, Value::String(value)) => message.interface = Some(value),
+                (FIELD_MEMBER, Value::String(value)) => message.member = Some(value),
+                (FIELD_ERROR_NAME, Value::String(value)) => message.error_name = Some(value),
+                (FIELD_REPLY_SERIAL, Value::U32(value)) => message.reply_serial = Some(value),
+                (FIELD_DESTINATION, Value::String(value)) => message.destination = Some(value),
+                (FIELD_SIGNATURE, Value::String(value)) => message.signature = value,
+                _ => {}
+            }
+        }
+        message.body = header[header.len() - body_len..].to_vec();
+        Ok(message)
+    }
+}
+
+/// A connection to a message bus
+#[derive(Debug)]
+pub struct Connection {
+    stream: UnixStream,
+    serial: u32,
+}
+
+impl Connection {
+    /// Connects and authenticates with the uid of the process, then
+    /// registers on the bus
+    pub fn open(address: &str) -> io::Result<Self> {
+        let mut stream = connect_socket(address)?;
+        stream.set_read_timeout(Some(TIMEOUT))?;
+        stream.set_write_timeout(Some(TIMEOUT))?;
+
+        let uid = std::fs::metadata("/proc/self")?.uid().to_string();
+        let hex_uid: String = uid.bytes().map(|b| format!("{:02x}", b)).collect();
+        stream.write_all(format!("\0AUTH EXTERNAL {}\r\n", hex_uid).as_bytes())?;
+        let reply = read_line(&mut stream)?;
+        if !reply.starts_with("OK ") {
+            return Err(io::Error::new(
+                io::ErrorKind::PermissionDenied,
+                format!("D-Bus authentication failed: {}", reply),
+            ));
+        }
+        stream.write_all(b"BEGIN\r\n")?;
+
+        let mut connection = Self { stream, serial: 0 };
+        connection.call(Message::method_call(
+            "org.freedesktop.DBus",
+            "/org/freedesktop/DBus",
+            "org.freedesktop.DBus",
+            "Hello",
+        ))?;
+        Ok(connection)
+    }
+
+    /// Sends a method call and waits for its reply
+    pub fn call(&mut self, mut message: Message) -> io::Result<Message> {
+        self.serial += 1;
+        message.serial = self.serial;
+        self.stream.write_all(&message.to_bytes())?;
+        loop {
+            let reply = Message::read_from(&mut self.stream)?;
+            if reply.reply_serial != Some(message.serial) {
+                // Signals such as NameAcquired
+                continue;
+            }
+            return match reply.message_type {
+                METHOD_RETURN => Ok(reply),
+                ERROR => {
+                    let detail = reply.body_reader().string().unwrap_or_default();
+                    Err(io::Error::other(format!(
+                        "{}: {}",
+                        reply.error_name.unwrap_or_default(),
+                        detail
+                    )))
+                }
+                _ => Err(invalid_data("unexpected D-Bus message type")),
+            };
+        }
+    }
+}
+
+/// Reads a line of the authentication protocol, without the line ending
+pub fn read_line(stream: &mut impl Read) -> io::Result<String> {
+    let mut line = Vec::new();
+    let mut byte = [0];
+    while !line.ends_with(b"\r\n") {
+        stream.read_exact(&mut byte)?;
+        line.push(byte[0]);
+    }
+    line.truncate(line.len() - 2);
+    Ok(String::from_utf8_lossy(&line).into_owned())
+}
+
+/// A desktop notification as specified by freedesktop.org
+#[derive(Debug, Clone, PartialEq)]
+pub struct Notification {
+    pub summary: String,
+    pub body: String,
+    /// 0 low, 1 normal, 2 critical
+    pub urgency: u8,
+}
+
+/// Shows a notification, returns its id
+pub fn notify(address: &str, app_name: &str, notification: &Notification) -> io::Result<u32> {
+    let mut connection = Connection::open(address)?;
+    let mut message = Message::method_call(
+        "org.freedesktop.Notifications",
+        "/org/freedesktop/Notifications",
+        "org.freedesktop.Notifications",
+        "Notify",
+    );
+    message.signature = "susssasa{sv}i".to_string();
+    let mut w = Writer::default();
+    w.string(app_name);
+    w.u32(0);
+    w.string("");
+    w.string(&notification.summary);
+    w.string(&notification.body);
+    w.array(4, |_| {});
+    w.array(8, |w| {
+        w.start_struct();
+        w.string("urgency");
+        w.signature("y");
+        w.byte(notification.urgency);
+    });
+    // Default expiration
+    w.i32(-1);
+    message.body = w.into_bytes();
+
+    let reply = connection.call(message)?;
+    reply.body_reader().u32()
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use std::os::unix::net::UnixListener;
+    use std::path::PathBuf;
+    use std::sync::mpsc;
+
+    /// Stand-in for a session bus with a notification daemon. Serves one
+    /// connection and sends the notifications it receives over the channel.
+    pub fn fake_bus(name: &str) -> (String, mpsc::Receiver<Notification>, PathBuf) {
+        let path =
+            std::env::temp_dir().join(format!("thinkfan-tui-bus-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let listener = UnixListener::bind(&path).unwrap();
+        let (sender, receiver) = mpsc::channel();
+        std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut nul = [0];
+            stream.read_exact(&mut nul).unwrap();
+            let auth = read_line(&mut stream).unwrap();
+            assert!(auth.starts_with("AUTH EXTERNAL "));
+            stream.write_all(b"OK 0123456789abcdef\r\n").unwrap();
+            assert_eq!(read_line(&mut stream).unwrap(), "BEGIN");
+
+            while let Ok(call) = Message::read_from(&mut stream) {
+                let reply = match call.member.as_deref() {
+                    Some("Hello") => {
+                        let mut w = Writer::default();
+                        w.string(":1.42");
+                        Message::method_return(&call, "s", w.into_bytes())
+                    }
+                    Some("Notify") => {
+                        assert_eq!(call.signature, "susssasa{sv}i");
+                        let mut r = call.body_reader();
+                        assert_eq!(r.string().unwrap(), "thinkfan-tui");
+                        assert_eq!(r.u32().unwrap(), 0);
+                        r.string().unwrap();
+                        let summary = r.string().unwrap();
+                        let body = r.string().unwrap();
+                        let end = r.array(4).unwrap();
+                        assert_eq!(r.position(), end);
+                        r.array(8).unwrap();
+                        r.start_struct();
+                        assert_eq!(r.string().unwrap(), "urgency");
+                        assert_eq!(r.signature().unwrap(), "y");
+                        let urgency = r.byte().unwrap();
+                        assert_eq!(r.i32().unwrap(), -1);
+                        sender
+                            .send(Notification {
+                                summary,
+                                body,
+                                urgency,
+                            })
+                            .unwrap();
+                        let mut w = Writer::default();
+                        w.u32(7);
+                        Message::method_return(&call, "u", w.into_bytes())
+                    }
+                    _ => continue,
+                };
+                let mut reply = reply;
+                reply.serial = call.serial + 1000;
+                stream.write_all(&reply.to_bytes()).unwrap();
+            }
+        });
+        (format!("unix:path={}", path.display()), receiver, path)
+    }
+
+    #[test]
+    fn message_round_trip() {
+        let mut message = Message::method_call("a.b", "/a/b", "a.b.C", "Method");
+        message.serial = 3;
+        message.signature = "s".to_string();
+        let mut w = Writer::default();
+        w.string("hello");
+        message.body = w.into_bytes();
+
+        let bytes = message.to_bytes();
+        let read = Message::read_from(&mut bytes.as_slice()).unwrap();
+        assert_eq!(read, message);
+        assert_eq!(read.body_reader().string().unwrap(), "hello");
+    }
+
+    #[test]
+    fn send_notification() {
+        let (address, notifications, path) = fake_bus("notify");
+        let notification = Notification {
+            summary: "CPU is critical".to_string(),
+            body: "96°C".to_string(),
+            urgency: 2,
+        };
+        assert_eq!(notify(&address, "thinkfan-tui", &notification).unwrap(), 7);
+        assert_eq!(notifications.recv().unwrap(), notification);
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn unsupported_address() {
+        assert!(connect_socket("tcp:host=localhost,port=1234").is_err());
+    }
+}
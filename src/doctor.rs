@@ -0,0 +1,305 @@
+use std::io::{self, Write};
+use std::path::Path;
+
+use crate::access::{self, Denied, State};
+use crate::daemons::{self, Daemon};
+use crate::error::Error;
+use crate::{hwmon, sensors};
+
+static PATH_MODULE: &str = "/sys/module/thinkpad_acpi";
+/// Persists `fan_control=1` across reboots
+static MODPROBE_SNIPPET: &str =
+    "  echo \"options thinkpad_acpi fan_control=1\" | sudo tee /etc/modprobe.d/thinkpad_acpi.conf";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Status {
+    Ok,
+    Warning,
+    Failed,
+}
+
+/// Result of one check, with the commands fixing a problem
+#[derive(Debug, Clone, PartialEq)]
+pub struct Check {
+    pub name: &'static str,
+    pub status: Status,
+    pub detail: String,
+    pub fix: Vec<String>,
+}
+
+impl Check {
+    fn ok(name: &'static str, detail: impl Into<String>) -> Self {
+        Self {
+            name,
+            status: Status::Ok,
+            detail: detail.into(),
+            fix: Vec::new(),
+        }
+    }
+
+    fn problem(
+        name: &'static str,
+        status: Status,
+        detail: impl Into<String>,
+        fix: Vec<String>,
+    ) -> Self {
+        Self {
+            name,
+            status,
+            detail: detail.into(),
+            fix,
+        }
+    }
+}
+
+/// Runs every check against the running system
+pub fn run() -> Vec<Check> {
+    let state = State::read(
+        Path::new(access::PATH_FAN),
+        Path::new(access::PATH_MODULE_FAN_CONTROL),
+        Path::new(access::PATH_LOCKDOWN),
+    );
+    vec![
+        check_module(Path::new(PATH_MODULE).exists()),
+        check_fan_control(&state),
+        check_fan_file(&state),
+        check_sensors(crate::read_sensors()),
+        check_hwmon(&hwmon::devices(Path::new(hwmon::PATH_HWMON))),
+        check_daemons(&daemons::detect()),
+        check_lockdown(state.lockdown.as_deref()),
+    ]
+}
+
+/// Prints the checks, returns whether all of them passed
+pub fn print(checks: &[Check], mut out: impl Write) -> io::Result<bool> {
+    for check in checks {
+        let status = match check.status {
+            Status::Ok => " ok ",
+            Status::Warning => "warn",
+            Status::Failed => "FAIL",
+        };
+        writeln!(out, "[{}] {}: {}", status, check.name, check.detail)?;
+        for line in &check.fix {
+            writeln!(out, "       {}", line)?;
+        }
+    }
+    Ok(checks.iter().all(|check| check.status != Status::Failed))
+}
+
+fn check_module(loaded: bool) -> Check {
+    const NAME: &str = "thinkpad_acpi";
+    if loaded {
+        return Check::ok(NAME, "loaded");
+    }
+    Check::problem(
+        NAME,
+        Status::Failed,
+        "not loaded",
+        vec![
+            "Load it with:".to_string(),
+            "  sudo modprobe thinkpad_acpi fan_control=1".to_string(),
+        ],
+    )
+}
+
+fn check_fan_control(state: &State) -> Check {
+    const NAME: &str = "fan_control";
+    match state.fan_control {
+        Some(true) => Check::ok(NAME, "enabled"),
+        Some(false) => Check::problem(
+            NAME,
+            Status::Failed,
+            "disabled, the fan can only be monitored",
+            vec![
+                "Reload thinkpad_acpi with fan control:".to_string(),
+                "  sudo modprobe -r thinkpad_acpi".to_string(),
+                "  sudo modprobe thinkpad_acpi fan_control=1".to_string(),
+                "Enable it on every boot with:".to_string(),
+                MODPROBE_SNIPPET.to_string(),
+            ],
+        ),
+        None => Check::problem(
+            NAME,
+            Status::Warning,
+            format!("{} is not readable", access::PATH_MODULE_FAN_CONTROL),
+            Vec::new(),
+        ),
+    }
+}
+
+fn check_fan_file(state: &State) -> Check {
+    const NAME: &str = "fan file";
+    match state.check() {
+        Ok(()) => Check::ok(NAME, format!("{} is writable", access::PATH_FAN)),
+        Err(denied @ Denied::Ownership { .. }) => Check::problem(
+            NAME,
+            Status::Warning,
+            format!("{}, thinkfan-tui asks for access on start", denied.reason()),
+            denied.steps().into_iter().skip(1).collect(),
+        ),
+        // Reported by the module and fan_control checks
+        Err(denied) => Check::problem(NAME, Status::Failed, denied.reason(), Vec::new()),
+    }
+}
+
+fn check_sensors(output: Result<String, Error>) -> Check {
+    const NAME: &str = "sensors";
+    let install = vec![
+        "Install lm-sensors, e.g. with:".to_string(),
+        "  sudo apt install lm-sensors".to_string(),
+        "  sudo pacman -S lm_sensors".to_string(),
+    ];
+    let json_str = match output {
+        Ok(json_str) => json_str,
+        Err(Error::SensorsNotFound) => {
+            return Check::problem(NAME, Status::Failed, "not installed", install)
+        }
+        Err(Error::SensorsFailed(stderr)) => {
+            return Check::problem(
+                NAME,
+                Status::Failed,
+                format!("`sensors -j` failed: {}", stderr),
+                vec![
+                    "Detect the sensors with:".to_string(),
+                    "  sudo sensors-detect".to_string(),
+                ],
+            )
+        }
+        Err(e) => return Check::problem(NAME, Status::Failed, e.to_string(), install),
+    };
+    match sensors::parse_adapters(&json_str) {
+        Ok(adapters) => Check::ok(NAME, format!("{} adapters", adapters.len())),
+        Err(_) => Check::problem(
+            NAME,
+            Status::Failed,
+            "`sensors -j` did not print JSON, lm-sensors 3.5 or newer is needed",
+            install,
+        ),
+    }
+}
+
+fn check_hwmon(devices: &[(String, String)]) -> Check {
+    const NAME: &str = "hwmon";
+    if devices.is_empty() {
+        return Check::problem(NAME, Status::Failed, "no devices", Vec::new());
+    }
+    let names: Vec<String> = devices
+        .iter()
+        .map(|(dir, name)| format!("{} ({})", name, dir))
+        .collect();
+    if !devices.iter().any(|(_, name)| name == "thinkpad") {
+        return Check::problem(
+            NAME,
+            Status::Warning,
+            format!("no thinkpad device, found {}", names.join(", ")),
+            Vec::new(),
+        );
+    }
+    Check::ok(NAME, names.join(", "))
+}
+
+fn check_daemons(running: &[Daemon]) -> Check {
+    const NAME: &str = "fan daemons";
+    if running.is_empty() {
+        return Check::ok(NAME, "none running");
+    }
+    let names: Vec<&str> = running.iter().map(|d| d.name.as_str()).collect();
+    let mut fix = vec!["They overwrite the levels set here, stop them with:".to_string()];
+    fix.extend(running.iter().map(|daemon| match &daemon.unit {
+        Some(unit) => format!("  sudo systemctl stop {}", unit),
+        None => format!("  sudo pkill -x {}", daemon.name),
+    }));
+    Check::problem(
+        NAME,
+        Status::Warning,
+        format!("{} running", names.join(", ")),
+        fix,
+    )
+}
+
+fn check_lockdown(lockdown: Option<&str>) -> Check {
+    const NAME: &str = "lockdown";
+    match lockdown {
+        None => Check::ok(NAME, "off"),
+        Some(mode) => Check::problem(
+            NAME,
+            Status::Warning,
+            format!("{}, usually because of secure boot", mode),
+            vec![
+                "Module parameters may be refused, check the kernel log with:".to_string(),
+                "  sudo dmesg | grep -i lockdown".to_string(),
+            ],
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn checks() {
+        let state = State {
+            fan: Some((0, 0o100644)),
+            writable: false,
+            fan_control: Some(false),
+            lockdown: None,
+        };
+        let check = check_fan_control(&state);
+        assert_eq!(check.status, Status::Failed);
+        assert!(check.fix.contains(&MODPROBE_SNIPPET.to_string()));
+        assert_eq!(check_fan_file(&state).status, Status::Failed);
+        let state = State {
+            fan_control: Some(true),
+            ..state
+        };
+        assert_eq!(check_fan_control(&state).status, Status::Ok);
+        let check = check_fan_file(&state);
+        assert_eq!(check.status, Status::Warning);
+        assert!(check.fix.last().unwrap().starts_with("  sudo chown "));
+
+        let json_str = std::fs::read_to_string("testdata/sensors-t490").unwrap();
+        assert_eq!(check_sensors(Ok(json_str)).detail, "6 adapters");
+        // Bare nan values are not valid JSON but are skipped like in the TUI
+        let json_str = std::fs::read_to_string("testdata/sensors-nan").unwrap();
+        assert_eq!(check_sensors(Ok(json_str)).status, Status::Ok);
+        assert_eq!(
+            check_sensors(Err(Error::SensorsNotFound)).detail,
+            "not installed"
+        );
+        let check = check_sensors(Err(Error::SensorsFailed("No sensors found!".into())));
+        assert_eq!(check.detail, "`sensors -j` failed: No sensors found!");
+        assert_eq!(
+            check_sensors(Ok("temp1: +45.0°C".into())).status,
+            Status::Failed
+        );
+
+        let devices = vec![("hwmon0".to_string(), "acpitz".to_string())];
+        assert_eq!(check_hwmon(&devices).status, Status::Warning);
+        assert_eq!(check_hwmon(&[]).status, Status::Failed);
+
+        let check = check_daemons(&[Daemon {
+            name: "thinkfan".into(),
+            unit: Some("thinkfan.service".into()),
+        }]);
+        assert_eq!(check.status, Status::Warning);
+        assert_eq!(check.fix[1], "  sudo systemctl stop thinkfan.service");
+        assert_eq!(check_lockdown(Some("integrity")).status, Status::Warning);
+    }
+
+    #[test]
+    fn report() {
+        let checks = vec![check_module(true), check_daemons(&[]), check_module(false)];
+        let mut out = Vec::new();
+        assert!(!print(&checks, &mut out).unwrap());
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "[ ok ] thinkpad_acpi: loaded\n\
+             [ ok ] fan daemons: none running\n\
+             [FAIL] thinkpad_acpi: not loaded\n       \
+             Load it with:\n       \
+             \x20 sudo modprobe thinkpad_acpi fan_control=1\n"
+        );
+        assert!(print(&checks[..2], io::sink()).unwrap());
+    }
+}
@@ -0,0 +1,41 @@
+use std::fmt;
+
+use crate::access::PATH_FAN;
+
+/// Errors reading the sensors and reading or writing the fan
+#[derive(Debug, Clone, PartialEq)]
+pub enum Error {
+    /// The `sensors` binary of lm-sensors is not installed
+    SensorsNotFound,
+    /// `sensors` could not be started
+    SensorsSpawn(String),
+    /// `sensors` failed without printing readings, with its error output
+    SensorsFailed(String),
+    /// The output of `sensors -j` is not a JSON object
+    SensorsOutput(String),
+    FanOpen(String),
+    FanRead(String),
+    /// `/proc/acpi/ibm/fan` does not have the expected lines
+    FanFormat(&'static str),
+    FanWrite {
+        command: String,
+        reason: String,
+    },
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::SensorsNotFound => write!(f, "sensors is not installed, install lm-sensors"),
+            Error::SensorsSpawn(e) => write!(f, "Failed to run sensors: {}", e),
+            Error::SensorsFailed(e) => write!(f, "sensors failed: {}", e),
+            Error::SensorsOutput(e) => write!(f, "Invalid output of sensors -j: {}", e),
+            Error::FanOpen(e) => write!(f, "Failed to open {}: {}", PATH_FAN, e),
+            Error::FanRead(e) => write!(f, "Failed to read {}: {}", PATH_FAN, e),
+            Error::FanFormat(e) => write!(f, "Unexpected content in {}: {}", PATH_FAN, e),
+            Error::FanWrite { command, reason } => {
+                write!(f, "Failed to write command '{}': {}", command, reason)
+            }
+        }
+    }
+}
@@ -0,0 +1,166 @@
+use std::collections::VecDeque;
+use std::fs::{File, OpenOptions};
+use std::io::{self, Write};
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Number of entries kept in memory, the log file keeps all of them
+const MAX_ENTRIES: usize = 1000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Category {
+    /// Commands written to the fan
+    Fan,
+    Error,
+    /// Threshold crossings and stopped fans
+    Alert,
+    /// Sensors appearing or disappearing
+    Sensor,
+}
+
+impl Category {
+    pub fn name(self) -> &'static str {
+        match self {
+            Category::Fan => "fan",
+            Category::Error => "error",
+            Category::Alert => "alert",
+            Category::Sensor => "sensor",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Entry {
+    /// Local time, e.g. "2026-10-18 14:03:12"
+    pub time: String,
+    pub category: Category,
+    pub message: String,
+}
+
+impl Entry {
+    /// The entry as a line of the log file
+    pub fn line(&self) -> String {
+        format!(
+            "{}  {:<6}  {}",
+            self.time,
+            self.category.name(),
+            self.message
+        )
+    }
+}
+
+/// Timestamped events of the session, optionally appended to a file
+#[derive(Debug, Default)]
+pub struct EventLog {
+    entries: VecDeque<Entry>,
+    file: Option<File>,
+}
+
+impl EventLog {
+    /// Appends the entries to a file as well, creating it and its directory
+    /// if needed
+    pub fn with_file(path: &Path) -> io::Result<Self> {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        Ok(Self {
+            entries: VecDeque::new(),
+            file: Some(file),
+        })
+    }
+
+    pub fn push(&mut self, category: Category, message: impl Into<String>) {
+        let entry = Entry {
+            time: format_local_time(SystemTime::now()),
+            category,
+            message: message.into(),
+        };
+        if let Some(file) = &mut self.file {
+            // The log is kept in memory if the file cannot be written
+            if writeln!(file, "{}", entry.line()).is_err() {
+                self.file = None;
+            }
+        }
+        if self.entries.len() == MAX_ENTRIES {
+            self.entries.pop_front();
+        }
+        self.entries.push_back(entry);
+    }
+
+    pub fn entries(&self) -> &VecDeque<Entry> {
+        &self.entries
+    }
+}
+
+/// Formats a time in the local time zone as "YYYY-MM-DD HH:MM:SS"
+pub fn format_local_time(time: SystemTime) -> String {
+    let secs = time
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0) as libc::time_t;
+    // SAFETY: localtime_r only writes to the given tm struct, which is
+    // valid to be zeroed
+    let mut tm: libc::tm = unsafe { std::mem::zeroed() };
+    if unsafe { libc::localtime_r(&secs, &mut tm) }.is_null() {
+        return secs.to_string();
+    }
+    format!(
+        "{:04}-{:02}-{:02} {:02}:{:02}:{:02}",
+        tm.tm_year + 1900,
+        tm.tm_mon + 1,
+        tm.tm_mday,
+        tm.tm_hour,
+        tm.tm_min,
+        tm.tm_sec
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn local_time() {
+        let time = format_local_time(UNIX_EPOCH + Duration::from_secs(1_792_000_000));
+        assert_eq!(time.len(), 19);
+        // The date depends on the time zone of the machine
+        assert!(time.starts_with("2026-10-1"));
+        assert_eq!(&time[13..14], ":");
+    }
+
+    #[test]
+    fn limited_entries() {
+        let mut log = EventLog::default();
+        for i in 0..MAX_ENTRIES + 5 {
+            log.push(Category::Fan, format!("level {}", i % 8));
+        }
+        assert_eq!(log.entries().len(), MAX_ENTRIES);
+        assert_eq!(log.entries()[0].message, "level 5");
+    }
+
+    #[test]
+    fn log_file() {
+        let dir = std::env::temp_dir().join(format!("thinkfan-tui-events-{}", std::process::id()));
+        let path = dir.join("state").join("events.log");
+        let _ = std::fs::remove_dir_all(&dir);
+
+        let mut log = EventLog::with_file(&path).unwrap();
+        log.push(Category::Fan, "Set level 3");
+        log.push(Category::Error, "Failed to write command");
+        drop(log);
+        let mut log = EventLog::with_file(&path).unwrap();
+        log.push(Category::Sensor, "nvme-pci-0100/Composite appeared");
+
+        let content = std::fs::read_to_string(&path).unwrap();
+        let lines: Vec<&str> = content.lines().collect();
+        assert_eq!(lines.len(), 3);
+        assert!(lines[0].ends_with("  fan     Set level 3"));
+        assert!(lines[2].ends_with("  sensor  nvme-pci-0100/Composite appeared"));
+        // Only the entries of this session are kept in memory
+        assert_eq!(log.entries().len(), 1);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,213 @@
+use std::path::{Path, PathBuf};
+
+use crate::helper::Helper;
+use crate::{hwmon, sensors};
+
+/// A fan reported by the thinkpad hwmon device
+#[derive(Debug, Clone, PartialEq)]
+pub struct Fan {
+    /// Label of the fan, e.g. "fan2"
+    pub label: String,
+    /// Number of the fan, as in `fan2_input`
+    pub index: u32,
+    pub rpm: u32,
+    /// `pwm<index>` file when the fans can be controlled on their own
+    pub pwm: Option<PathBuf>,
+}
+
+impl Fan {
+    /// Level of the fan read back from its pwm files, e.g. "auto"
+    pub fn level(&self) -> Option<String> {
+        let pwm = self.pwm.as_ref()?;
+        let read = |path: &Path| -> Option<u32> {
+            std::fs::read_to_string(path).ok()?.trim().parse().ok()
+        };
+        match read(&enable_path(pwm))? {
+            0 => Some("full-speed".to_string()),
+            1 => Some((read(pwm)? >> 5).min(7).to_string()),
+            _ => Some("auto".to_string()),
+        }
+    }
+
+    /// Writes a command of `/proc/acpi/ibm/fan`, e.g. "level 3", to the pwm
+    /// files of the fan, through the helper if one is running
+    pub fn write_command(
+        &self,
+        command: &str,
+        mut helper: Option<&mut Helper>,
+    ) -> Result<(), String> {
+        let pwm = self
+            .pwm
+            .as_ref()
+            .ok_or_else(|| format!("{} cannot be controlled on its own", self.label))?;
+        let (enable, value) =
+            pwm_values(command).ok_or_else(|| format!("Unsupported command '{}'", command))?;
+        let mut write = |path: &Path, value: &str| {
+            let result = match &mut helper {
+                Some(helper) => {
+                    let file = path.file_name().unwrap_or_default().to_string_lossy();
+                    helper.write(&format!("{} {}", file, value))
+                }
+                None => std::fs::write(path, value).map_err(|e| e.to_string()),
+            };
+            result.map_err(|e| format!("{}: {}", path.display(), e))
+        };
+        write(&enable_path(pwm), enable)?;
+        if let Some(value) = value {
+            write(pwm, &value)?;
+        }
+        Ok(())
+    }
+}
+
+/// `pwm<n>_enable` next to a `pwm<n>` file
+fn enable_path(pwm: &Path) -> PathBuf {
+    let mut path = pwm.as_os_str().to_owned();
+    path.push("_enable");
+    PathBuf::from(path)
+}
+
+/// Values of `pwm<n>_enable` and `pwm<n>` for a fan command. thinkpad_acpi
+/// takes 0 for full speed, 1 for manual and 2 for auto, and scales the
+/// 0-255 pwm value down to levels 0-7.
+fn pwm_values(command: &str) -> Option<(&'static str, Option<String>)> {
+    match command.strip_prefix("level ")? {
+        "auto" => Some(("2", None)),
+        "full-speed" | "disengaged" => Some(("0", None)),
+        level => {
+            let level: u32 = level.parse().ok().filter(|&l| l <= 7)?;
+            Some(("1", Some((level * 255 / 7).to_string())))
+        }
+    }
+}
+
+/// Finds the fans of the thinkpad adapter in the `sensors -j` output and
+/// their pwm files below `hwmon_root`. On models with two fans thinkpad_acpi
+/// usually only has `pwm1`, which sets the level of both fans, so the pwm
+/// files are only kept if every fan has one.
+pub fn parse_fans(json_str: &str, hwmon_root: &Path) -> Vec<Fan> {
+    let json_str = sensors::replace_non_finite(json_str);
+    let Ok(serde_json::Value::Object(json)) = serde_json::from_str(&json_str) else {
+        return Vec::new();
+    };
+    let mut fans = Vec::new();
+    for (adapter_name, adapter) in json
+        .iter()
+        .filter(|(name, _)| name.starts_with("thinkpad-"))
+    {
+        let Some(adapter) = adapter.as_object() else {
+            continue;
+        };
+        for (label, input) in adapter {
+            let Some(input) = input.as_object() else {
+                continue;
+            };
+            for (key, value) in input {
+                let Some(index) = key
+                    .strip_prefix("fan")
+                    .and_then(|k| k.strip_suffix("_input"))
+                    .and_then(|i| i.parse().ok())
+                else {
+                    continue;
+                };
+                fans.push(Fan {
+                    label: label.clone(),
+                    index,
+                    rpm: value.as_f64().unwrap_or(0.0) as u32,
+                    pwm: hwmon::find_input_path(hwmon_root, adapter_name, &format!("pwm{}", index)),
+                });
+            }
+        }
+    }
+    fans.sort_by_key(|fan| fan.index);
+    if fans.iter().any(|fan| fan.pwm.is_none()) {
+        for fan in &mut fans {
+            fan.pwm = None;
+        }
+    }
+    fans
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn commands() {
+        assert_eq!(pwm_values("level auto"), Some(("2", None)));
+        assert_eq!(pwm_values("level full-speed"), Some(("0", None)));
+        assert_eq!(pwm_values("level 0"), Some(("1", Some("0".to_string()))));
+        // Scaled down again by the driver with `pwm >> 5`
+        for level in 0..=7 {
+            let (_, value) = pwm_values(&format!("level {}", level)).unwrap();
+            assert_eq!(value.unwrap().parse::<u32>().unwrap() >> 5, level);
+        }
+        assert_eq!(pwm_values("level 8"), None);
+        assert_eq!(pwm_values("enable"), None);
+    }
+
+    #[test]
+    fn dual_fans() {
+        let root = std::env::temp_dir().join(format!("thinkfan-tui-fans-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+        let dir = root.join("hwmon3");
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("name"), "thinkpad\n").unwrap();
+        std::fs::write(dir.join("pwm1"), "0\n").unwrap();
+        std::fs::write(dir.join("pwm1_enable"), "2\n").unwrap();
+
+        let json_str = std::fs::read_to_string("testdata/sensors-t14s-amd-gen1").unwrap();
+        let fans = parse_fans(&json_str, &root);
+        assert_eq!(fans.len(), 2);
+        assert_eq!(fans[0].label, "fan1");
+        assert_eq!(fans[0].rpm, 3000);
+        assert_eq!(fans[1].label, "fan2");
+        // pwm1 controls both fans
+        assert_eq!(fans[0].pwm, None);
+        assert!(fans[0].write_command("level 3", None).is_err());
+
+        std::fs::write(dir.join("pwm2"), "0\n").unwrap();
+        std::fs::write(dir.join("pwm2_enable"), "2\n").unwrap();
+        let fans = parse_fans(&json_str, &root);
+        assert_eq!(fans[0].pwm, Some(dir.join("pwm1")));
+        assert_eq!(fans[1].pwm, Some(dir.join("pwm2")));
+
+        assert_eq!(fans[0].level().as_deref(), Some("auto"));
+        fans[0].write_command("level 3", None).unwrap();
+        assert_eq!(std::fs::read_to_string(dir.join("pwm1")).unwrap(), "109");
+        assert_eq!(fans[0].level().as_deref(), Some("3"));
+        fans[1].write_command("level full-speed", None).unwrap();
+        assert_eq!(fans[1].level().as_deref(), Some("full-speed"));
+
+        // The helper is given the file names only
+        let log = root.join("helper.log");
+        let mut command = std::process::Command::new("sh");
+        command.arg("-c").arg(format!(
+            "echo ready; while read -r line; do echo \"$line\" >> {}; echo ok; done",
+            log.display()
+        ));
+        let mut helper = Helper::spawn(command).unwrap();
+        fans[1].write_command("level 7", Some(&mut helper)).unwrap();
+        fans[1]
+            .write_command("level auto", Some(&mut helper))
+            .unwrap();
+        drop(helper);
+        assert_eq!(
+            std::fs::read_to_string(&log).unwrap(),
+            "pwm2_enable 1\npwm2 255\npwm2_enable 2\n"
+        );
+
+        // The ACPI fan device of the gen 4 is not a thinkpad fan
+        let json_str = std::fs::read_to_string("testdata/sensors-t14s-amd-gen4").unwrap();
+        assert_eq!(parse_fans(&json_str, &root).len(), 2);
+        let json_str = std::fs::read_to_string("testdata/sensors-t490").unwrap();
+        assert_eq!(parse_fans(&json_str, &root).len(), 1);
+        // A nan reading of another chip does not hide the fans
+        let json_str = std::fs::read_to_string("testdata/sensors-nan").unwrap();
+        let fans = parse_fans(&json_str, &root);
+        assert_eq!(fans.len(), 1);
+        assert_eq!(fans[0].rpm, 2900);
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -0,0 +1,300 @@
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+/// Least change in RPM that counts as the fan reacting. Readings of a fan
+/// running at a fixed level jitter by a few RPM.
+const MIN_CHANGE: u32 = 100;
+
+/// Rise of the hottest sensor in °C that the fan has to react to in auto mode
+const FLAT_RISE: f64 = 10.0;
+
+/// Time span the fan has to react to a temperature rise in auto mode
+const FLAT_WINDOW: Duration = Duration::from_secs(120);
+
+/// Kind of a probable fan fault
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Fault {
+    /// The fan reports 0 RPM at a level other than 0 or auto
+    Stalled,
+    /// The RPM did not rise after the level was raised
+    NoResponse,
+    /// The RPM stays flat in auto mode while the temperature rises
+    Flat,
+}
+
+impl Fault {
+    /// Name used in the hook environment
+    pub fn name(self) -> &'static str {
+        match self {
+            Fault::Stalled => "stalled",
+            Fault::NoResponse => "no-response",
+            Fault::Flat => "flat",
+        }
+    }
+}
+
+/// Rank of a fan level for comparing levels, `None` for auto whose speed is
+/// up to the firmware
+fn rank(level: &str) -> Option<u8> {
+    match level {
+        "full-speed" | "disengaged" => Some(8),
+        level => level.parse().ok().filter(|&n| n <= 7),
+    }
+}
+
+/// Watches the fan level and speed for a fan that does not do what it is
+/// told. A detected fault is kept until the speed changes or the fan is
+/// turned off.
+#[derive(Debug)]
+pub struct FaultDetector {
+    /// Time the fan is given to react to a new level
+    response: Duration,
+    level: Option<String>,
+    level_since: Option<Instant>,
+    rpm: Option<u32>,
+    /// Time of the last step up and the RPM before it
+    step_up: Option<(Instant, u32)>,
+    /// Hottest temperature and RPM in auto mode over `FLAT_WINDOW`
+    samples: VecDeque<(Instant, f64, u32)>,
+    /// Detected fault and the RPM at that time
+    fault: Option<(Fault, u32)>,
+    /// Highest RPM seen, a fan running that fast cannot speed up any more
+    top_rpm: u32,
+}
+
+impl FaultDetector {
+    pub fn new(response: Duration) -> Self {
+        Self {
+            response,
+            level: None,
+            level_since: None,
+            rpm: None,
+            step_up: None,
+            samples: VecDeque::new(),
+            fault: None,
+            top_rpm: 0,
+        }
+    }
+
+    /// Whether a fan at this speed may still speed up. Many models run
+    /// several levels at the same speed, e.g. 7 and full-speed.
+    fn can_speed_up(&self, rpm: u32) -> bool {
+        rpm + MIN_CHANGE <= self.top_rpm
+    }
+
+    /// Checks a reading of the fan level, its speed and the hottest
+    /// temperature in °C, returns the current fault
+    pub fn update(
+        &mut self,
+        level: Option<&str>,
+        rpm: Option<u32>,
+        temp: Option<f64>,
+        now: Instant,
+    ) -> Option<Fault> {
+        let (Some(level), Some(rpm)) = (level, rpm) else {
+            *self = Self {
+                top_rpm: self.top_rpm,
+                ..Self::new(self.response)
+            };
+            return None;
+        };
+        self.top_rpm = self.top_rpm.max(rpm);
+
+        if self.level.as_deref() != Some(level) {
+            let previous = self.level.replace(level.to_string());
+            self.level_since = Some(now);
+            self.samples.clear();
+            self.step_up = match (previous.as_deref().and_then(rank), rank(level)) {
+                (Some(from), Some(to)) if to > from => Some((now, self.rpm.unwrap_or(rpm))),
+                _ => None,
+            };
+        }
+        self.rpm = Some(rpm);
+
+        if let Some((_, fault_rpm)) = self.fault {
+            if rpm.abs_diff(fault_rpm) >= MIN_CHANGE || rank(level) == Some(0) {
+                self.fault = None;
+            }
+        }
+
+        let settled = self
+            .level_since
+            .is_some_and(|since| now.duration_since(since) >= self.response);
+        if settled && rpm == 0 && rank(level).is_some_and(|r| r > 0) && self.fault.is_none() {
+            self.fault = Some((Fault::Stalled, rpm));
+        }
+        if let Some((time, before)) = self.step_up {
+            if rpm >= before + MIN_CHANGE {
+                self.step_up = None;
+            } else if now.duration_since(time) >= self.response {
+                self.step_up = None;
+                if self.fault.is_none() && self.can_speed_up(before) {
+                    self.fault = Some((Fault::NoResponse, rpm));
+                }
+            }
+        }
+
+        // Manual levels hold the speed by design, only auto has to react
+        match temp {
+            Some(temp) if level == "auto" && rpm > 0 => {
+                self.samples.push_back((now, temp, rpm));
+                while self
+                    .samples
+                    .front()
+                    .is_some_and(|(time, _, _)| now.duration_since(*time) > FLAT_WINDOW)
+                {
+                    self.samples.pop_front();
+                }
+                let coolest = self.samples.iter().map(|s| s.1).fold(temp, f64::min);
+                let slowest = self.samples.iter().map(|s| s.2).min().unwrap_or(rpm);
+                let fastest = self.samples.iter().map(|s| s.2).max().unwrap_or(rpm);
+                if temp - coolest >= FLAT_RISE
+                    && fastest - slowest < MIN_CHANGE
+                    && self.can_speed_up(fastest)
+                    && self.fault.is_none()
+                {
+                    self.fault = Some((Fault::Flat, rpm));
+                }
+            }
+            _ => self.samples.clear(),
+        }
+
+        self.fault.map(|(fault, _)| fault)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stalled() {
+        let mut detector = FaultDetector::new(Duration::from_secs(10));
+        let start = Instant::now();
+        let mut update = |level, rpm, secs| {
+            detector.update(
+                Some(level),
+                Some(rpm),
+                None,
+                start + Duration::from_secs(secs),
+            )
+        };
+        // Auto and level 0 may stop the fan
+        assert_eq!(update("auto", 0, 0), None);
+        assert_eq!(update("0", 0, 20), None);
+        // The fan is given time to spin up
+        assert_eq!(update("3", 0, 40), None);
+        assert_eq!(update("3", 0, 45), None);
+        assert_eq!(update("3", 0, 50), Some(Fault::Stalled));
+        // Kept when falling back to full speed, cleared once it spins
+        assert_eq!(update("full-speed", 0, 51), Some(Fault::Stalled));
+        assert_eq!(update("full-speed", 4500, 55), None);
+    }
+
+    #[test]
+    fn no_response() {
+        let mut detector = FaultDetector::new(Duration::from_secs(10));
+        let start = Instant::now();
+        let mut update = |level, rpm, secs| {
+            detector.update(
+                Some(level),
+                Some(rpm),
+                None,
+                start + Duration::from_secs(secs),
+            )
+        };
+        // The fan was seen at full speed before
+        assert_eq!(update("full-speed", 4500, 0), None);
+        assert_eq!(update("2", 2400, 0), None);
+        assert_eq!(update("5", 2400, 1), None);
+        assert_eq!(update("5", 2430, 10), None);
+        assert_eq!(update("5", 2420, 11), Some(Fault::NoResponse));
+        assert_eq!(update("5", 2420, 12), Some(Fault::NoResponse));
+        // Stepping down is not checked
+        assert_eq!(update("1", 2420, 13), Some(Fault::NoResponse));
+        assert_eq!(update("0", 2420, 14), None);
+
+        assert_eq!(update("2", 2400, 20), None);
+        assert_eq!(update("7", 2400, 21), None);
+        assert_eq!(update("7", 3900, 25), None);
+        assert_eq!(update("7", 3900, 40), None);
+    }
+
+    #[test]
+    fn no_response_at_top_speed() {
+        let mut detector = FaultDetector::new(Duration::from_secs(10));
+        let start = Instant::now();
+        let mut update = |level, rpm, secs| {
+            detector.update(
+                Some(level),
+                Some(rpm),
+                None,
+                start + Duration::from_secs(secs),
+            )
+        };
+        // Level 7 and full-speed share a speed
+        assert_eq!(update("7", 4500, 0), None);
+        assert_eq!(update("full-speed", 4520, 1), None);
+        assert_eq!(update("full-speed", 4510, 20), None);
+        // Lower levels still have to speed the fan up
+        assert_eq!(update("3", 3000, 21), None);
+        assert_eq!(update("4", 3000, 22), None);
+        assert_eq!(update("4", 3000, 40), Some(Fault::NoResponse));
+    }
+
+    #[test]
+    fn flat_in_auto() {
+        let mut detector = FaultDetector::new(Duration::from_secs(10));
+        let start = Instant::now();
+        let mut update = |level, rpm, temp, secs| {
+            detector.update(
+                Some(level),
+                Some(rpm),
+                Some(temp),
+                start + Duration::from_secs(secs),
+            )
+        };
+        assert_eq!(update("7", 4500, 50.0, 0), None);
+        assert_eq!(update("auto", 2500, 50.0, 0), None);
+        assert_eq!(update("auto", 2510, 55.0, 30), None);
+        assert_eq!(update("auto", 2490, 61.0, 60), Some(Fault::Flat));
+        assert_eq!(update("auto", 2500, 62.0, 200), Some(Fault::Flat));
+        assert_eq!(update("auto", 3600, 62.0, 201), None);
+        // A slow rise is not a fault
+        assert_eq!(update("auto", 3600, 64.0, 400), None);
+        assert_eq!(update("auto", 3600, 74.0, 600), None);
+        // Neither is the same speed at a manual level
+        assert_eq!(update("4", 3600, 74.0, 601), None);
+        assert_eq!(update("4", 3600, 90.0, 602), None);
+    }
+
+    #[test]
+    fn flat_at_top_speed() {
+        let mut detector = FaultDetector::new(Duration::from_secs(10));
+        let start = Instant::now();
+        let mut update = |rpm, temp, secs| {
+            detector.update(
+                Some("auto"),
+                Some(rpm),
+                Some(temp),
+                start + Duration::from_secs(secs),
+            )
+        };
+        // The EC already runs the fan as fast as it goes
+        assert_eq!(update(4500, 70.0, 0), None);
+        assert_eq!(update(4480, 80.0, 60), None);
+        assert_eq!(update(4490, 85.0, 90), None);
+    }
+
+    #[test]
+    fn no_fan_info() {
+        let mut detector = FaultDetector::new(Duration::from_secs(0));
+        let now = Instant::now();
+        assert_eq!(
+            detector.update(Some("7"), Some(0), None, now),
+            Some(Fault::Stalled)
+        );
+        assert_eq!(detector.update(None, Some(0), None, now), None);
+        assert_eq!(detector.update(Some("7"), None, None, now), None);
+    }
+}
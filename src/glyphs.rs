@@ -0,0 +1,134 @@
+use ratatui::{buffer::Buffer, layout::Rect};
+
+/// Characters used to draw bars, markers and the scrollbar
+#[derive(Debug, PartialEq)]
+pub struct Glyphs {
+    pub bar: &'static str,
+    pub bar_empty: &'static str,
+    /// Marker next to a temperature for cool, warm and hot readings
+    pub severity: [&'static str; 3],
+    pub expanded: &'static str,
+    pub collapsed: &'static str,
+    pub scroll_up: &'static str,
+    pub scroll_down: &'static str,
+    pub thumb: &'static str,
+    pub track: &'static str,
+    /// Text cursor of input lines
+    pub cursor: &'static str,
+    pub degree: &'static str,
+}
+
+pub static UNICODE: Glyphs = Glyphs {
+    bar: "▀",
+    bar_empty: "▀",
+    severity: ["▊", "▊", "▊"],
+    expanded: "▾",
+    collapsed: "▸",
+    scroll_up: "▲",
+    scroll_down: "▼",
+    thumb: "█",
+    track: "░",
+    cursor: "█",
+    degree: "°",
+};
+
+/// For terminals and fonts without the block and box drawing characters.
+/// Severity is written out since the colours may not be visible either.
+pub static ASCII: Glyphs = Glyphs {
+    bar: "#",
+    bar_empty: ".",
+    severity: ["ok  ", "warm", "HOT "],
+    expanded: "-",
+    collapsed: "+",
+    scroll_up: "^",
+    scroll_down: "v",
+    thumb: "#",
+    track: "|",
+    cursor: "_",
+    degree: "",
+};
+
+/// Guesses whether the terminal can only show ASCII from `TERM` and the
+/// locale, given as the first set of `LC_ALL`, `LC_CTYPE` and `LANG`. The
+/// Linux console and serial terminals often lack the glyphs.
+pub fn detect_ascii(term: Option<&str>, locale: Option<&str>) -> bool {
+    let term = term.unwrap_or("dumb");
+    if term == "dumb" || term == "linux" || term.starts_with("vt") {
+        return true;
+    }
+    let locale = locale.unwrap_or("C").to_ascii_lowercase();
+    !(locale.contains("utf-8") || locale.contains("utf8"))
+}
+
+pub fn detect_ascii_from_env() -> bool {
+    let var = |name| std::env::var(name).ok().filter(|v| !v.is_empty());
+    let term = var("TERM");
+    let locale = var("LC_ALL")
+        .or_else(|| var("LC_CTYPE"))
+        .or_else(|| var("LANG"));
+    detect_ascii(term.as_deref(), locale.as_deref())
+}
+
+/// ASCII replacement of a symbol drawn by ratatui or found in labels, such as
+/// borders, chart dots and arrows in key names
+fn ascii_symbol(symbol: &str) -> &str {
+    let mut chars = symbol.chars();
+    let (Some(c), None) = (chars.next(), chars.next()) else {
+        return if symbol.is_ascii() { symbol } else { "?" };
+    };
+    match c {
+        c if c.is_ascii() => symbol,
+        '─' | '━' | '═' | '–' | '—' => "-",
+        '│' | '┃' | '║' => "|",
+        '\u{2500}'..='\u{257f}' => "+",
+        '\u{2800}' => " ",
+        '\u{2801}'..='\u{28ff}' | '•' => "*",
+        '\u{2580}'..='\u{259f}' => "#",
+        '↑' | '▲' => "^",
+        '↓' | '▼' | '▾' => "v",
+        '←' => "<",
+        '→' | '▸' => ">",
+        _ => "?",
+    }
+}
+
+/// Replaces the remaining non-ASCII symbols in an area of the buffer
+pub fn to_ascii(buf: &mut Buffer, area: Rect) {
+    for y in area.top()..area.bottom() {
+        for x in area.left()..area.right() {
+            let cell = &mut buf[(x, y)];
+            if !cell.symbol().is_ascii() {
+                let symbol = ascii_symbol(cell.symbol()).to_string();
+                cell.set_symbol(&symbol);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detect() {
+        assert!(!detect_ascii(Some("xterm-256color"), Some("en_US.UTF-8")));
+        assert!(!detect_ascii(Some("foot"), Some("sv_SE.utf8")));
+        assert!(detect_ascii(Some("xterm-256color"), Some("C")));
+        assert!(detect_ascii(Some("xterm-256color"), None));
+        assert!(detect_ascii(Some("linux"), Some("en_US.UTF-8")));
+        assert!(detect_ascii(Some("vt220"), Some("en_US.UTF-8")));
+        assert!(detect_ascii(None, Some("en_US.UTF-8")));
+    }
+
+    #[test]
+    fn symbols() {
+        assert_eq!(ascii_symbol("a"), "a");
+        assert_eq!(ascii_symbol("━"), "-");
+        assert_eq!(ascii_symbol("┃"), "|");
+        assert_eq!(ascii_symbol("┏"), "+");
+        assert_eq!(ascii_symbol("⣿"), "*");
+        assert_eq!(ascii_symbol("\u{2800}"), " ");
+        assert_eq!(ascii_symbol("↑"), "^");
+        assert_eq!(ascii_symbol("é"), "?");
+    }
+}
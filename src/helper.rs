@@ -0,0 +1,113 @@
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Child, ChildStdin, ChildStdout, Command, Stdio};
+
+/// Where the privileged helper is installed. pkexec only shows the prompt
+/// of the polkit policy for the path annotated there.
+static PATH_HELPER: &str = "/usr/libexec/thinkfan-tui-helper";
+
+/// Finds the installed helper
+pub fn find() -> Option<PathBuf> {
+    Some(PathBuf::from(PATH_HELPER)).filter(|path| path.exists())
+}
+
+/// Connection to the privileged helper writing the fan commands
+#[derive(Debug)]
+pub struct Helper {
+    child: Child,
+    /// Closed when dropped, which makes the helper exit
+    stdin: Option<ChildStdin>,
+    stdout: BufReader<ChildStdout>,
+}
+
+impl Helper {
+    /// Starts the helper with pkexec, which asks for authentication in the
+    /// terminal if there is no graphical polkit agent. Returns once the
+    /// helper is running.
+    pub fn start(path: &Path) -> io::Result<Self> {
+        let mut command = Command::new("pkexec");
+        command.arg(path);
+        Self::spawn(command)
+    }
+
+    /// Starts the helper with `command`, used with a fake helper in tests
+    pub fn spawn(mut command: Command) -> io::Result<Self> {
+        let mut child = command
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()?;
+        let stdin = child.stdin.take();
+        let stdout = BufReader::new(child.stdout.take().expect("stdout is piped"));
+        let mut helper = Self {
+            child,
+            stdin,
+            stdout,
+        };
+        // Sent after pkexec authenticated the user, which may be refused
+        let mut line = String::new();
+        helper.stdout.read_line(&mut line)?;
+        if line.trim_end() != "ready" {
+            return Err(io::Error::new(
+                io::ErrorKind::PermissionDenied,
+                "the helper was not started, authentication failed or was cancelled",
+            ));
+        }
+        Ok(helper)
+    }
+
+    /// Sends a command and waits for the answer of the helper
+    pub fn write(&mut self, command: &str) -> Result<(), String> {
+        let stdin = self.stdin.as_mut().ok_or("helper exited")?;
+        writeln!(stdin, "{}", command)
+            .and_then(|_| stdin.flush())
+            .map_err(|e| match e.kind() {
+                io::ErrorKind::BrokenPipe => "helper exited".to_string(),
+                _ => format!("helper: {}", e),
+            })?;
+        let mut answer = String::new();
+        match self.stdout.read_line(&mut answer) {
+            Ok(0) => Err("helper exited".to_string()),
+            Ok(_) => match answer.trim_end().strip_prefix("error ") {
+                Some(error) => Err(error.to_string()),
+                None => Ok(()),
+            },
+            Err(e) => Err(format!("helper: {}", e)),
+        }
+    }
+}
+
+impl Drop for Helper {
+    fn drop(&mut self) {
+        // The helper runs as root and cannot be killed, it exits once its
+        // stdin is closed
+        drop(self.stdin.take());
+        let _ = self.child.wait();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fake_helper() {
+        let mut command = Command::new("sh");
+        command.arg("-c").arg(
+            "echo ready; while read -r line; do \
+                 case \"$line\" in level*) echo ok ;; *) echo \"error invalid command\" ;; esac; \
+             done",
+        );
+        let mut helper = Helper::spawn(command).unwrap();
+        assert_eq!(helper.write("level 3"), Ok(()));
+        assert_eq!(helper.write("rm -rf"), Err("invalid command".to_string()));
+
+        let mut command = Command::new("sh");
+        command.arg("-c").arg("echo ready");
+        let mut helper = Helper::spawn(command).unwrap();
+        assert_eq!(helper.write("level 3"), Err("helper exited".to_string()));
+
+        // pkexec exits with 126 without output if authentication fails
+        let error = Helper::spawn(Command::new("true")).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::PermissionDenied);
+    }
+}
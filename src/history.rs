@@ -0,0 +1,129 @@
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+/// Number of samples kept per sensor, ten minutes at the default interval
+const MAX_SAMPLES: usize = 600;
+
+/// Time span used to calculate the rate of change
+const RATE_WINDOW: Duration = Duration::from_secs(10);
+
+/// Readings of a single sensor during the session
+#[derive(Debug, Clone)]
+pub struct SensorHistory {
+    samples: VecDeque<(Instant, f64)>,
+    min: f64,
+    max: f64,
+    sum: f64,
+    count: usize,
+}
+
+impl Default for SensorHistory {
+    fn default() -> Self {
+        Self {
+            samples: VecDeque::with_capacity(MAX_SAMPLES),
+            min: f64::INFINITY,
+            max: f64::NEG_INFINITY,
+            sum: 0.0,
+            count: 0,
+        }
+    }
+}
+
+impl SensorHistory {
+    pub fn push(&mut self, time: Instant, temp: f64) {
+        if self.samples.len() == MAX_SAMPLES {
+            self.samples.pop_front();
+        }
+        self.samples.push_back((time, temp));
+        self.min = self.min.min(temp);
+        self.max = self.max.max(temp);
+        self.sum += temp;
+        self.count += 1;
+    }
+
+    /// Lowest reading of the session
+    pub fn min(&self) -> Option<f64> {
+        (self.count > 0).then_some(self.min)
+    }
+
+    /// Highest reading of the session
+    pub fn max(&self) -> Option<f64> {
+        (self.count > 0).then_some(self.max)
+    }
+
+    /// Average of all readings of the session
+    pub fn avg(&self) -> Option<f64> {
+        (self.count > 0).then(|| self.sum / self.count as f64)
+    }
+
+    /// Change in °C per second over the last few seconds
+    pub fn rate(&self) -> Option<f64> {
+        let &(last_time, last_temp) = self.samples.back()?;
+        let &(first_time, first_temp) = self
+            .samples
+            .iter()
+            .find(|(time, _)| last_time.duration_since(*time) <= RATE_WINDOW)?;
+        let elapsed = last_time.duration_since(first_time).as_secs_f64();
+        (elapsed > 0.0).then(|| (last_temp - first_temp) / elapsed)
+    }
+
+    /// Samples as (seconds relative to the latest sample, temperature), for
+    /// plotting
+    pub fn points(&self) -> Vec<(f64, f64)> {
+        let Some(&(last_time, _)) = self.samples.back() else {
+            return Vec::new();
+        };
+        self.samples
+            .iter()
+            .map(|(time, temp)| (-last_time.duration_since(*time).as_secs_f64(), *temp))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn session_stats() {
+        let mut history = SensorHistory::default();
+        assert_eq!(history.min(), None);
+        assert_eq!(history.avg(), None);
+        assert_eq!(history.rate(), None);
+
+        let start = Instant::now();
+        for (secs, temp) in [(0, 40.0), (1, 50.0), (2, 45.0)] {
+            history.push(start + Duration::from_secs(secs), temp);
+        }
+        assert_eq!(history.min(), Some(40.0));
+        assert_eq!(history.max(), Some(50.0));
+        assert_eq!(history.avg(), Some(45.0));
+        assert_eq!(history.rate(), Some(2.5));
+        assert_eq!(
+            history.points(),
+            vec![(-2.0, 40.0), (-1.0, 50.0), (0.0, 45.0)]
+        );
+    }
+
+    #[test]
+    fn rate_uses_recent_samples() {
+        let mut history = SensorHistory::default();
+        let start = Instant::now();
+        history.push(start, 90.0);
+        history.push(start + Duration::from_secs(60), 40.0);
+        history.push(start + Duration::from_secs(64), 42.0);
+        assert_eq!(history.rate(), Some(0.5));
+    }
+
+    #[test]
+    fn limited_samples() {
+        let mut history = SensorHistory::default();
+        let start = Instant::now();
+        for i in 0..MAX_SAMPLES + 10 {
+            history.push(start + Duration::from_secs(i as u64), i as f64);
+        }
+        assert_eq!(history.points().len(), MAX_SAMPLES);
+        // Session stats still cover all samples
+        assert_eq!(history.min(), Some(0.0));
+    }
+}
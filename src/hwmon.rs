@@ -0,0 +1,125 @@
+use std::path::{Path, PathBuf};
+
+pub static PATH_HWMON: &str = "/sys/class/hwmon";
+
+/// Finds the sysfs file of an lm-sensors input, e.g. `temp1_input` of
+/// `nvme-pci-0100`. Chip names have the form `<name>-<bus>-<address>` where
+/// `<name>` is the content of the `name` file of the hwmon device. PCI
+/// addresses are used to tell apart devices sharing a name.
+pub fn find_input_path(root: &Path, adapter_name: &str, input_key: &str) -> Option<PathBuf> {
+    let mut parts = adapter_name.rsplitn(3, '-');
+    let address = parts.next()?;
+    let bus = parts.next()?;
+    let name = parts.next()?;
+
+    let mut candidates: Vec<PathBuf> = std::fs::read_dir(root)
+        .ok()?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|dir| std::fs::read_to_string(dir.join("name")).is_ok_and(|n| n.trim() == name))
+        .collect();
+    candidates.sort();
+
+    let dir = if bus == "pci" && candidates.len() > 1 {
+        let pci_suffix = pci_device_suffix(address)?;
+        candidates.into_iter().find(|dir| {
+            std::fs::read_link(dir.join("device"))
+                .is_ok_and(|link| link.to_string_lossy().ends_with(&pci_suffix))
+        })?
+    } else {
+        candidates.into_iter().next()?
+    };
+
+    let path = dir.join(input_key);
+    path.exists().then_some(path)
+}
+
+/// Directory and device names of the hwmon devices, e.g. ("hwmon3",
+/// "thinkpad")
+pub fn devices(root: &Path) -> Vec<(String, String)> {
+    let Ok(entries) = std::fs::read_dir(root) else {
+        return Vec::new();
+    };
+    let mut devices: Vec<(String, String)> = entries
+        .filter_map(|entry| {
+            let path = entry.ok()?.path();
+            let name = std::fs::read_to_string(path.join("name")).ok()?;
+            Some((
+                path.file_name()?.to_string_lossy().into_owned(),
+                name.trim().to_string(),
+            ))
+        })
+        .collect();
+    devices.sort();
+    devices
+}
+
+/// Converts the address libsensors uses for PCI chips, `(bus << 8) | devfn`
+/// in hex, to the end of a PCI device name, e.g. `0100` to `01:00.0`
+fn pci_device_suffix(address: &str) -> Option<String> {
+    let address = u16::from_str_radix(address, 16).ok()?;
+    let bus = address >> 8;
+    let devfn = address & 0xff;
+    Some(format!("{:02x}:{:02x}.{:x}", bus, devfn >> 3, devfn & 0x7))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn create_device(root: &Path, hwmon: &str, name: &str, device: Option<&str>) {
+        let dir = root.join(hwmon);
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("name"), format!("{}\n", name)).unwrap();
+        std::fs::write(dir.join("temp1_input"), "45000\n").unwrap();
+        if let Some(device) = device {
+            std::os::unix::fs::symlink(format!("../../../{}", device), dir.join("device")).unwrap();
+        }
+    }
+
+    #[test]
+    fn find_paths() {
+        let root = std::env::temp_dir().join(format!("thinkfan-tui-hwmon-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+        create_device(&root, "hwmon0", "acpitz", None);
+        create_device(&root, "hwmon1", "nvme", Some("0000:05:00.0"));
+        create_device(&root, "hwmon2", "nvme", Some("0000:01:00.0"));
+        create_device(&root, "hwmon3", "iwlwifi_1", None);
+
+        assert_eq!(
+            find_input_path(&root, "acpitz-acpi-0", "temp1_input"),
+            Some(root.join("hwmon0/temp1_input"))
+        );
+        assert_eq!(
+            find_input_path(&root, "nvme-pci-0100", "temp1_input"),
+            Some(root.join("hwmon2/temp1_input"))
+        );
+        assert_eq!(
+            find_input_path(&root, "nvme-pci-0500", "temp1_input"),
+            Some(root.join("hwmon1/temp1_input"))
+        );
+        assert_eq!(
+            find_input_path(&root, "iwlwifi_1-virtual-0", "temp1_input"),
+            Some(root.join("hwmon3/temp1_input"))
+        );
+        assert_eq!(find_input_path(&root, "acpitz-acpi-0", "temp2_input"), None);
+        assert_eq!(
+            find_input_path(&root, "k10temp-pci-00c3", "temp1_input"),
+            None
+        );
+        assert_eq!(find_input_path(&root, "invalid", "temp1_input"), None);
+        assert_eq!(
+            devices(&root)[2],
+            ("hwmon2".to_string(), "nvme".to_string())
+        );
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn pci_suffix() {
+        assert_eq!(pci_device_suffix("0100").as_deref(), Some("01:00.0"));
+        assert_eq!(pci_device_suffix("00c3").as_deref(), Some("00:18.3"));
+        assert_eq!(pci_device_suffix("c300").as_deref(), Some("c3:00.0"));
+        assert_eq!(pci_device_suffix("xyz"), None);
+    }
+}
@@ -0,0 +1,596 @@
+use std::collections::HashMap;
+use std::fmt;
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use serde::Deserialize;
+
+/// Help section of the actions controlling the fan
+pub static FAN_CONTROL: &str = "Fan control";
+
+/// Everything a key can be bound to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Action {
+    FanLevel0,
+    FanLevel1,
+    FanLevel2,
+    FanLevel3,
+    FanLevel4,
+    FanLevel5,
+    FanLevel6,
+    FanLevel7,
+    FanAuto,
+    FanFull,
+    FanDisengaged,
+    FanEnable,
+    FanDisable,
+    ToggleWatchdog,
+    SelectFan,
+    PauseDaemons,
+    ToggleSort,
+    ToggleUnit,
+    CollapseAll,
+    CursorUp,
+    CursorDown,
+    PageUp,
+    PageDown,
+    Top,
+    Bottom,
+    Collapse,
+    Expand,
+    Activate,
+    ToggleHidden,
+    Pin,
+    Hide,
+    Rename,
+    Control,
+    ToggleHelp,
+    ToggleLog,
+    Close,
+    Quit,
+}
+
+impl Action {
+    /// All actions in the order they are listed in the help window
+    pub const ALL: [Action; 37] = [
+        Action::FanLevel0,
+        Action::FanLevel1,
+        Action::FanLevel2,
+        Action::FanLevel3,
+        Action::FanLevel4,
+        Action::FanLevel5,
+        Action::FanLevel6,
+        Action::FanLevel7,
+        Action::FanAuto,
+        Action::FanFull,
+        Action::FanDisengaged,
+        Action::FanEnable,
+        Action::FanDisable,
+        Action::ToggleWatchdog,
+        Action::SelectFan,
+        Action::PauseDaemons,
+        Action::ToggleSort,
+        Action::ToggleUnit,
+        Action::CollapseAll,
+        Action::CursorUp,
+        Action::CursorDown,
+        Action::PageUp,
+        Action::PageDown,
+        Action::Top,
+        Action::Bottom,
+        Action::Collapse,
+        Action::Expand,
+        Action::Activate,
+        Action::ToggleHidden,
+        Action::Pin,
+        Action::Hide,
+        Action::Rename,
+        Action::Control,
+        Action::ToggleHelp,
+        Action::ToggleLog,
+        Action::Close,
+        Action::Quit,
+    ];
+
+    /// Fan level set by the action, for the level 0-7 actions
+    pub fn fan_level(self) -> Option<u8> {
+        match self {
+            Action::FanLevel0 => Some(0),
+            Action::FanLevel1 => Some(1),
+            Action::FanLevel2 => Some(2),
+            Action::FanLevel3 => Some(3),
+            Action::FanLevel4 => Some(4),
+            Action::FanLevel5 => Some(5),
+            Action::FanLevel6 => Some(6),
+            Action::FanLevel7 => Some(7),
+            _ => None,
+        }
+    }
+
+    pub fn section(self) -> &'static str {
+        match self {
+            Action::FanLevel0
+            | Action::FanLevel1
+            | Action::FanLevel2
+            | Action::FanLevel3
+            | Action::FanLevel4
+            | Action::FanLevel5
+            | Action::FanLevel6
+            | Action::FanLevel7
+            | Action::FanAuto
+            | Action::FanFull
+            | Action::FanDisengaged
+            | Action::FanEnable
+            | Action::FanDisable
+            | Action::ToggleWatchdog
+            | Action::SelectFan
+            | Action::PauseDaemons => FAN_CONTROL,
+            Action::Pin | Action::Hide | Action::Rename | Action::Control => "Sensor details",
+            Action::ToggleHelp | Action::ToggleLog | Action::Close | Action::Quit => "Global",
+            _ => "Temperature",
+        }
+    }
+
+    /// Whether the action controls the fan, which is not possible in
+    /// read-only mode
+    pub fn is_fan_control(self) -> bool {
+        self.section() == FAN_CONTROL
+    }
+
+    pub fn description(self) -> &'static str {
+        match self {
+            Action::FanLevel0
+            | Action::FanLevel1
+            | Action::FanLevel2
+            | Action::FanLevel3
+            | Action::FanLevel4
+            | Action::FanLevel5
+            | Action::FanLevel6
+            | Action::FanLevel7 => "Specific level",
+            Action::FanAuto => "Auto",
+            Action::FanFull => "Full speed",
+            Action::FanDisengaged => "Disengaged",
+            Action::FanEnable => "Enable fan",
+            Action::FanDisable => "Disable fan",
+            Action::ToggleWatchdog => "Toggle watchdog",
+            Action::SelectFan => "Select fan",
+            Action::PauseDaemons => "Pause/resume fan daemons",
+            Action::ToggleSort => "Toggle sorting",
+            Action::ToggleUnit => "Toggle unit",
+            Action::CollapseAll => "Collapse/expand all",
+            Action::CursorUp => "Cursor up",
+            Action::CursorDown => "Cursor down",
+            Action::PageUp => "Page up",
+            Action::PageDown => "Page down",
+            Action::Top => "Go to top",
+            Action::Bottom => "Go to bottom",
+            Action::Collapse => "Collapse group",
+            Action::Expand => "Expand group",
+            Action::Activate => "Sensor details",
+            Action::ToggleHidden => "Show hidden sensors",
+            Action::Pin => "Pin/unpin",
+            Action::Hide => "Hide/unhide",
+            Action::Rename => "Rename",
+            Action::Control => "Control sensor",
+            Action::ToggleHelp => "Toggle help window",
+            Action::ToggleLog => "Toggle event log",
+            Action::Close => "Close window",
+            Action::Quit => "Quit",
+        }
+    }
+}
+
+/// Sets of bindings to start from
+#[derive(Debug, Default, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Preset {
+    #[default]
+    Default,
+    Vim,
+}
+
+/// The `[keys]` section of the config file. Bindings listed for an action
+/// replace the bindings of the preset for that action, e.g.
+/// `quit = ["q", "Ctrl-c"]`.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct KeysConfig {
+    pub preset: Preset,
+    #[serde(flatten)]
+    pub bindings: HashMap<Action, Vec<String>>,
+}
+
+static DEFAULT_BINDINGS: &[(Action, &[&str])] = &[
+    (Action::FanLevel0, &["0"]),
+    (Action::FanLevel1, &["1"]),
+    (Action::FanLevel2, &["2"]),
+    (Action::FanLevel3, &["3"]),
+    (Action::FanLevel4, &["4"]),
+    (Action::FanLevel5, &["5"]),
+    (Action::FanLevel6, &["6"]),
+    (Action::FanLevel7, &["7"]),
+    (Action::FanAuto, &["a"]),
+    (Action::FanFull, &["f"]),
+    (Action::FanDisengaged, &["d"]),
+    (Action::FanEnable, &["+"]),
+    (Action::FanDisable, &["-"]),
+    (Action::ToggleWatchdog, &["w"]),
+    (Action::SelectFan, &["n"]),
+    (Action::PauseDaemons, &["P"]),
+    (Action::ToggleSort, &["s"]),
+    (Action::ToggleUnit, &["t"]),
+    (Action::CollapseAll, &["c"]),
+    (Action::CursorUp, &["Up"]),
+    (Action::CursorDown, &["Down"]),
+    (Action::PageUp, &["PageUp"]),
+    (Action::PageDown, &["PageDown"]),
+    (Action::Top, &["Home"]),
+    (Action::Bottom, &["End"]),
+    (Action::Collapse, &["Left"]),
+    (Action::Expand, &["Right"]),
+    (Action::Activate, &["Enter"]),
+    (Action::ToggleHidden, &["H"]),
+    (Action::Pin, &["p"]),
+    (Action::Hide, &["x"]),
+    (Action::Rename, &["r"]),
+    (Action::Control, &["u"]),
+    (Action::ToggleHelp, &["?"]),
+    (Action::ToggleLog, &["e"]),
+    (Action::Close, &["Esc"]),
+    (Action::Quit, &["q"]),
+];
+
+// Added to the default bindings by the vim preset
+static VIM_BINDINGS: &[(Action, &[&str])] = &[
+    (Action::CursorUp, &["k"]),
+    (Action::CursorDown, &["j"]),
+    (Action::PageUp, &["Ctrl-u"]),
+    (Action::PageDown, &["Ctrl-d"]),
+    (Action::Top, &["g"]),
+    (Action::Bottom, &["G"]),
+    (Action::Collapse, &["h"]),
+    (Action::Expand, &["l"]),
+];
+
+/// A key with modifiers. Shift is not stored for characters since it is part
+/// of the character itself.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct KeyBinding {
+    pub code: KeyCode,
+    pub modifiers: KeyModifiers,
+}
+
+impl KeyBinding {
+    pub fn new(code: KeyCode, modifiers: KeyModifiers) -> Self {
+        let modifiers = match code {
+            KeyCode::Char(_) => modifiers - KeyModifiers::SHIFT,
+            _ => modifiers,
+        };
+        Self { code, modifiers }
+    }
+
+    /// Parses keys like "q", "G", "Ctrl-d", "PageUp" or "F1"
+    pub fn parse(s: &str) -> Result<Self, String> {
+        let mut chars = s.chars();
+        if let (Some(c), None) = (chars.next(), chars.next()) {
+            return Ok(Self::new(KeyCode::Char(c), KeyModifiers::NONE));
+        }
+
+        if let Some((modifier, rest)) = s.split_once('-').filter(|(_, rest)| !rest.is_empty()) {
+            let key = Self::parse(rest)?;
+            let modifier = match modifier.to_ascii_lowercase().as_str() {
+                "ctrl" => KeyModifiers::CONTROL,
+                "alt" => KeyModifiers::ALT,
+                "shift" => match key.code {
+                    KeyCode::Char(c) => {
+                        return Ok(Self::new(
+                            KeyCode::Char(c.to_ascii_uppercase()),
+                            key.modifiers,
+                        ))
+                    }
+                    _ => KeyModifiers::SHIFT,
+                },
+                _ => return Err(format!("unknown modifier '{}' in key '{}'", modifier, s)),
+            };
+            return Ok(Self::new(key.code, key.modifiers | modifier));
+        }
+
+        let code = match s.to_ascii_lowercase().as_str() {
+            "up" => KeyCode::Up,
+            "down" => KeyCode::Down,
+            "left" => KeyCode::Left,
+            "right" => KeyCode::Right,
+            "pageup" | "pgup" => KeyCode::PageUp,
+            "pagedown" | "pgdn" => KeyCode::PageDown,
+            "home" => KeyCode::Home,
+            "end" => KeyCode::End,
+            "enter" => KeyCode::Enter,
+            "esc" | "escape" => KeyCode::Esc,
+            "tab" => KeyCode::Tab,
+            "backspace" => KeyCode::Backspace,
+            "delete" => KeyCode::Delete,
+            "space" => KeyCode::Char(' '),
+            f if f.starts_with('f') && f[1..].parse::<u8>().is_ok() => {
+                KeyCode::F(f[1..].parse().unwrap())
+            }
+            _ => return Err(format!("unknown key '{}'", s)),
+        };
+        Ok(Self::new(code, KeyModifiers::NONE))
+    }
+}
+
+impl From<KeyEvent> for KeyBinding {
+    fn from(key_event: KeyEvent) -> Self {
+        Self::new(key_event.code, key_event.modifiers)
+    }
+}
+
+impl fmt::Display for KeyBinding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.modifiers.contains(KeyModifiers::CONTROL) {
+            write!(f, "Ctrl-")?;
+        }
+        if self.modifiers.contains(KeyModifiers::ALT) {
+            write!(f, "Alt-")?;
+        }
+        if self.modifiers.contains(KeyModifiers::SHIFT) {
+            write!(f, "Shift-")?;
+        }
+        match self.code {
+            KeyCode::Char(' ') => write!(f, "Space"),
+            KeyCode::Char(c) => write!(f, "{}", c),
+            KeyCode::Up => write!(f, "↑"),
+            KeyCode::Down => write!(f, "↓"),
+            KeyCode::Left => write!(f, "←"),
+            KeyCode::Right => write!(f, "→"),
+            KeyCode::PageUp => write!(f, "PgUp"),
+            KeyCode::PageDown => write!(f, "PgDn"),
+            KeyCode::F(n) => write!(f, "F{}", n),
+            code => write!(f, "{}", code),
+        }
+    }
+}
+
+/// Maps keys to actions
+#[derive(Debug)]
+pub struct Keymap {
+    bindings: Vec<(Action, Vec<KeyBinding>)>,
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        Self::new(&KeysConfig::default()).expect("default bindings are valid")
+    }
+}
+
+impl Keymap {
+    pub fn new(config: &KeysConfig) -> Result<Self, String> {
+        let parse_all = |keys: &[&str]| -> Result<Vec<KeyBinding>, String> {
+            keys.iter().map(|key| KeyBinding::parse(key)).collect()
+        };
+
+        let mut bindings = Vec::new();
+        for (action, keys) in DEFAULT_BINDINGS {
+            bindings.push((*action, parse_all(keys)?));
+        }
+
+        if config.preset == Preset::Vim {
+            for (action, keys) in VIM_BINDINGS {
+                let extra = parse_all(keys)?;
+                if let Some((_, keys)) = bindings.iter_mut().find(|(a, _)| a == action) {
+                    keys.extend(extra);
+                }
+            }
+        }
+
+        for (action, keys) in &config.bindings {
+            let keys: Vec<KeyBinding> = keys
+                .iter()
+                .map(|key| KeyBinding::parse(key))
+                .collect::<Result<_, _>>()?;
+            // A key moved to another action is taken from its old action
+            for (other, other_keys) in bindings.iter_mut() {
+                if other == action {
+                    *other_keys = keys.clone();
+                } else if !config.bindings.contains_key(other) {
+                    other_keys.retain(|key| !keys.contains(key));
+                }
+            }
+        }
+
+        Ok(Self { bindings })
+    }
+
+    pub fn action(&self, key_event: KeyEvent) -> Option<Action> {
+        let key = KeyBinding::from(key_event);
+        self.bindings
+            .iter()
+            .find(|(_, keys)| keys.contains(&key))
+            .map(|(action, _)| *action)
+    }
+
+    pub fn keys(&self, action: Action) -> &[KeyBinding] {
+        self.bindings
+            .iter()
+            .find(|(a, _)| *a == action)
+            .map(|(_, keys)| keys.as_slice())
+            .unwrap_or_default()
+    }
+
+    /// Sections of the help window, with the keys and description of each
+    /// bound action. Levels bound to the keys 0-7 are listed as one line.
+    pub fn help_sections(&self) -> Vec<(&'static str, Vec<(String, &'static str)>)> {
+        let mut sections: Vec<(&'static str, Vec<(String, &'static str)>)> = Vec::new();
+
+        let levels: Vec<&[KeyBinding]> = Action::ALL
+            .iter()
+            .filter(|a| a.fan_level().is_some())
+            .map(|a| self.keys(*a))
+            .collect();
+        let default_levels = levels.iter().enumerate().all(|(level, keys)| {
+            keys.len() == 1
+                && keys[0]
+                    == KeyBinding::new(
+                        KeyCode::Char((b'0' + level as u8) as char),
+                        KeyModifiers::NONE,
+                    )
+        });
+
+        for action in Action::ALL {
+            let keys = self.keys(action);
+            if keys.is_empty() {
+                continue;
+            }
+            let entry = match action.fan_level() {
+                Some(0) if default_levels => ("0–7".to_string(), action.description()),
+                Some(_) if default_levels => continue,
+                Some(level) => (join_keys(keys), LEVEL_DESCRIPTIONS[level as usize]),
+                None => (join_keys(keys), action.description()),
+            };
+            match sections
+                .iter_mut()
+                .find(|(name, _)| *name == action.section())
+            {
+                Some((_, entries)) => entries.push(entry),
+                None => sections.push((action.section(), vec![entry])),
+            }
+        }
+        sections
+    }
+}
+
+static LEVEL_DESCRIPTIONS: [&str; 8] = [
+    "Level 0", "Level 1", "Level 2", "Level 3", "Level 4", "Level 5", "Level 6", "Level 7",
+];
+
+fn join_keys(keys: &[KeyBinding]) -> String {
+    keys.iter()
+        .map(|key| key.to_string())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(code: KeyCode, modifiers: KeyModifiers) -> KeyEvent {
+        KeyEvent::new(code, modifiers)
+    }
+
+    #[test]
+    fn parse_keys() {
+        let parse = |s| KeyBinding::parse(s).unwrap();
+        assert_eq!(
+            parse("q"),
+            KeyBinding::new(KeyCode::Char('q'), KeyModifiers::NONE)
+        );
+        assert_eq!(parse("Shift-g"), parse("G"));
+        assert_eq!(
+            parse("-"),
+            KeyBinding::new(KeyCode::Char('-'), KeyModifiers::NONE)
+        );
+        assert_eq!(
+            parse("Ctrl-d"),
+            KeyBinding::new(KeyCode::Char('d'), KeyModifiers::CONTROL)
+        );
+        assert_eq!(parse("pgdn"), parse("PageDown"));
+        assert_eq!(parse("F1").code, KeyCode::F(1));
+        assert!(KeyBinding::parse("Hyper-x").is_err());
+        assert!(KeyBinding::parse("Foo").is_err());
+    }
+
+    #[test]
+    fn display_keys() {
+        for s in [
+            "q", "G", "Ctrl-d", "PgUp", "↑", "Enter", "Esc", "Space", "F5",
+        ] {
+            let parsed = match s {
+                "↑" => KeyBinding::parse("Up").unwrap(),
+                s => KeyBinding::parse(s).unwrap(),
+            };
+            assert_eq!(parsed.to_string(), s);
+        }
+    }
+
+    #[test]
+    fn default_keymap() {
+        let keymap = Keymap::default();
+        assert_eq!(
+            keymap.action(key(KeyCode::Char('q'), KeyModifiers::NONE)),
+            Some(Action::Quit)
+        );
+        // Terminals report shift for upper case characters and symbols
+        assert_eq!(
+            keymap.action(key(KeyCode::Char('?'), KeyModifiers::SHIFT)),
+            Some(Action::ToggleHelp)
+        );
+        assert_eq!(
+            keymap.action(key(KeyCode::Char('H'), KeyModifiers::SHIFT)),
+            Some(Action::ToggleHidden)
+        );
+        assert_eq!(
+            keymap.action(key(KeyCode::Char('j'), KeyModifiers::NONE)),
+            None
+        );
+    }
+
+    #[test]
+    fn vim_preset() {
+        let config: KeysConfig = toml::from_str("preset = \"vim\"").unwrap();
+        let keymap = Keymap::new(&config).unwrap();
+        assert_eq!(
+            keymap.action(key(KeyCode::Char('j'), KeyModifiers::NONE)),
+            Some(Action::CursorDown)
+        );
+        assert_eq!(
+            keymap.action(key(KeyCode::Down, KeyModifiers::NONE)),
+            Some(Action::CursorDown)
+        );
+        assert_eq!(
+            keymap.action(key(KeyCode::Char('d'), KeyModifiers::CONTROL)),
+            Some(Action::PageDown)
+        );
+        assert_eq!(
+            keymap.action(key(KeyCode::Char('G'), KeyModifiers::SHIFT)),
+            Some(Action::Bottom)
+        );
+    }
+
+    #[test]
+    fn custom_bindings() {
+        let config: KeysConfig =
+            toml::from_str("preset = \"vim\"\nquit = [\"Ctrl-c\", \"j\"]").unwrap();
+        let keymap = Keymap::new(&config).unwrap();
+        assert_eq!(
+            keymap.action(key(KeyCode::Char('q'), KeyModifiers::NONE)),
+            None
+        );
+        assert_eq!(
+            keymap.action(key(KeyCode::Char('j'), KeyModifiers::NONE)),
+            Some(Action::Quit)
+        );
+        assert_eq!(join_keys(keymap.keys(Action::CursorDown)), "↓");
+
+        let config: KeysConfig = toml::from_str("quit = [\"Foo\"]").unwrap();
+        assert!(Keymap::new(&config).is_err());
+        assert!(toml::from_str::<KeysConfig>("launch = [\"l\"]").is_err());
+    }
+
+    #[test]
+    fn help_sections() {
+        let sections = Keymap::default().help_sections();
+        let names: Vec<_> = sections.iter().map(|(name, _)| *name).collect();
+        assert_eq!(
+            names,
+            vec!["Fan control", "Temperature", "Sensor details", "Global"]
+        );
+        assert_eq!(sections[0].1[0], ("0–7".to_string(), "Specific level"));
+        assert_eq!(sections[3].1[3], ("q".to_string(), "Quit"));
+
+        let config: KeysConfig = toml::from_str("fan_level0 = [\"F10\"]").unwrap();
+        let sections = Keymap::new(&config).unwrap().help_sections();
+        assert_eq!(sections[0].1[0], ("F10".to_string(), "Level 0"));
+        assert_eq!(sections[0].1[1], ("1".to_string(), "Level 1"));
+    }
+}
@@ -1,41 +1,94 @@
-use std::time::Duration;
+mod access;
+mod alerts;
+mod backoff;
+mod cli;
+mod commands;
+mod config;
+mod daemons;
+mod dbus;
+mod doctor;
+mod error;
+mod events;
+mod fans;
+mod fault;
+mod glyphs;
+mod helper;
+mod history;
+mod hwmon;
+mod keymap;
+mod models;
+mod safety;
+mod sampler;
+mod sensors;
+mod theme;
+mod thermal;
+mod tree;
+mod units;
+
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+use std::rc::Rc;
+use std::time::{Duration, Instant};
 
 use std::fs::File;
 use std::io;
 use std::io::Read;
-use std::process::Command;
-
-use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind};
+use std::process::{Command, Stdio};
+
+use access::{Denied, PATH_FAN};
+use alerts::{Alerts, Reading};
+use backoff::Backoff;
+use commands::Commands;
+use crossterm::event::{
+    self, DisableFocusChange, DisableMouseCapture, EnableFocusChange, EnableMouseCapture, Event,
+    KeyCode, KeyEvent, KeyEventKind, MouseButton, MouseEvent, MouseEventKind,
+};
+use daemons::Daemon;
+use error::Error;
+use events::{Category, EventLog};
+use glyphs::Glyphs;
+use helper::Helper;
+use history::SensorHistory;
+use keymap::{Action, Keymap};
 use ratatui::{
     buffer::Buffer,
-    layout::Rect,
+    layout::{Position, Rect},
     style::Stylize,
-    symbols::border,
+    symbols::{border, Marker},
     text::{Line, Text},
-    widgets::{Block, Clear, Padding, Paragraph, Widget},
+    widgets::{Axis, Block, Chart, Clear, Dataset, GraphType, Padding, Paragraph, Widget},
     DefaultTerminal, Frame,
 };
+use safety::{Policy, Safety};
+use sampler::{Pace, PollingConfig, Request, Sampler, Snapshot};
+use sensors::{parse_adapters, Adapter, Input};
+use theme::{ColorSupport, Theme};
+use units::Unit;
 
 use ratatui::prelude::Constraint;
 use ratatui::prelude::Layout;
 
 use ratatui::prelude::Alignment;
-use ratatui::style::Color;
+use ratatui::style::Modifier;
 use ratatui::style::Style;
 use ratatui::text::Span;
 
-static PATH_FAN: &str = "/proc/acpi/ibm/fan";
-static PATH_MODULE_FAN_CONTROL: &str = "/sys/module/thinkpad_acpi/parameters/fan_control";
-
-// Light (foreground) filled bar colors
-static GREEN_LIGHT: Color = Color::Rgb(165, 183, 0); // #a5b700
-static YELLOW_LIGHT: Color = Color::Rgb(227, 168, 43); // #e3a82b
-static RED_LIGHT: Color = Color::Rgb(204, 31, 26); // #cc1f1a
-
-// Dark (background) bar colors
-static GREEN_DARK: Color = Color::Rgb(68, 68, 37); // #444425
-static YELLOW_DARK: Color = Color::Rgb(94, 78, 40); // #5e4e28
-static RED_DARK: Color = Color::Rgb(76, 32, 32); // #4c2020
+/// Most decimals of temperatures that fit the layout
+const MAX_PRECISION: usize = 3;
+
+// Labels and commands of the clickable fan level buttons
+static FAN_BUTTONS: &[(&str, &str)] = &[
+    ("0", "level 0"),
+    ("1", "level 1"),
+    ("2", "level 2"),
+    ("3", "level 3"),
+    ("4", "level 4"),
+    ("5", "level 5"),
+    ("6", "level 6"),
+    ("7", "level 7"),
+    ("Auto", "level auto"),
+    ("Full", "level full-speed"),
+];
 
 #[derive(Debug)]
 enum Sorting {
@@ -44,102 +97,254 @@ enum Sorting {
 }
 
 fn main() -> io::Result<()> {
-    if !fan_control_enabled() {
-        eprintln!("Error: thinkpad_acpi module not loaded with fan_control=1");
-        eprintln!("To reload it temporarily, run the following commands:");
-        eprintln!("sudo modprobe -r thinkpad_acpi");
-        eprintln!("sudo modprobe thinkpad_acpi fan_control=1");
+    let args = match cli::parse(std::env::args().skip(1)) {
+        Ok(args) => args,
+        Err(err) => {
+            eprintln!("Error: {}", err);
+            eprintln!();
+            eprintln!("{}", cli::USAGE);
+            std::process::exit(2);
+        }
+    };
+    if args.help {
+        println!("{}", env!("CARGO_PKG_DESCRIPTION"));
+        println!();
+        println!("{}", cli::USAGE);
         return Ok(());
     }
+    if args.version {
+        println!("thinkfan-tui {}", env!("CARGO_PKG_VERSION"));
+        return Ok(());
+    }
+    if args.doctor {
+        let passed = doctor::print(&doctor::run(), io::stdout().lock())?;
+        std::process::exit(if passed { 0 } else { 1 });
+    }
 
-    if !check_permissions() {
-        if let Err(err) = update_permissions() {
-            eprintln!("Error: could not update permissions:");
+    let mut app = match config::load().and_then(|config| App::from_config(&config, &args)) {
+        Ok(app) => app,
+        Err(err) => {
+            eprintln!("Error: could not load config:");
             eprintln!("{}", err);
             return Ok(());
         }
+    };
+
+    if args.read_only {
+        app.read_only = Some("started with --read-only".to_string());
+    } else if let Err(denied) = access::check() {
+        // The helper is started before the TUI so pkexec can ask for the
+        // password. Without it the fix-it window shows the steps.
+        let helper = match (&denied, helper::find()) {
+            (Denied::Ownership { .. }, Some(path)) => match Helper::start(&path) {
+                Ok(helper) => Some(helper),
+                Err(e) => {
+                    app.set_error(format!("Could not start {}: {}", path.display(), e));
+                    None
+                }
+            },
+            _ => None,
+        };
+        match helper {
+            Some(helper) => app.helper = Some(helper),
+            None => app.deny(denied),
+        }
     }
 
     let mut terminal = ratatui::init();
-    let app_result = App::new().run(&mut terminal);
+    crossterm::execute!(io::stdout(), EnableMouseCapture, EnableFocusChange)?;
+    let app_result = app.run(&mut terminal);
+    crossterm::execute!(io::stdout(), DisableMouseCapture, DisableFocusChange)?;
     ratatui::restore();
+    // Fan daemons are only paused for the session
+    if !app.paused_units.is_empty() {
+        daemons::systemctl("start", &app.paused_units).status()?;
+    }
     app_result
 }
 
-#[derive(Debug, Default, Clone)]
-pub struct Input {
-    name: String,
-    temp: f64,
-}
-
-#[derive(Debug, Default, Clone)]
-pub struct Adapter {
-    name: String,
-    inputs: Vec<Input>,
-}
-
 #[derive(Debug)]
 pub struct App {
     exit: bool,
     show_help: bool,
     lines: Vec<String>,
     adapters: Vec<Adapter>,
-    fan_command: &'static str,
+    /// Command written to the fan after the next events
+    fan_command: String,
+    /// Commands the driver accepts, unknown if `None`
+    fan_commands: Option<Commands>,
+    /// Risky command waiting for the user to confirm it
+    confirmation: Option<Confirmation>,
+    /// Timeout of the driver watchdog in seconds, if it was turned on
+    watchdog: Option<u32>,
+    safety: Safety,
+    /// Time a manual level goes back to auto
+    revert_at: Option<Instant>,
     current_error: String,
+    /// Size of the terminal at the last draw
+    area: Rect,
     visible_rows: usize,
+    /// Index of the first visible tree row
     scroll_offset: usize,
-    start_row: usize,
-    end_row: usize,
+    /// Number of tree rows that fit in the Temperatures panel
+    page_rows: usize,
+    /// Id of the tree row under the cursor
+    selected: Option<String>,
+    /// Scroll the cursor into view on the next draw
+    follow_cursor: bool,
+    /// The scrollbar thumb is being dragged with the mouse
+    dragging_scrollbar: bool,
+    tree: tree::TreeState,
     sorting: Sorting,
+    model: Option<&'static models::Model>,
+    /// Id of the sensor shown in the detail pane
+    detail: Option<String>,
+    /// Alias being typed in the detail pane
+    alias_input: Option<String>,
+    /// User given names of sensors by id
+    aliases: HashMap<String, String>,
+    /// Id of the sensor used to control the fan
+    control_sensor: Option<String>,
+    history: HashMap<String, SensorHistory>,
+    keymap: Keymap,
+    theme: Theme,
+    glyphs: &'static Glyphs,
+    unit: Unit,
+    /// Number of decimals of temperatures
+    precision: usize,
+    alerts: Alerts,
+    events: EventLog,
+    show_log: bool,
+    /// Lines the event log is scrolled up from the newest entry
+    log_scroll: usize,
+    /// Ids of the sensors of the last reading
+    known_sensors: HashSet<String>,
+    fans: Vec<fans::Fan>,
+    /// Index of the fan levels are written to, all fans if `None`
+    fan_target: Option<usize>,
+    /// Privileged helper writing the fan commands if the fan file is not
+    /// writable
+    helper: Option<Helper>,
+    /// Why the fan cannot be controlled, if the app only monitors
+    read_only: Option<String>,
+    /// Problem shown in the fix-it window until it is closed
+    access_problem: Option<Denied>,
+    /// Fan daemons found running at the last check
+    daemons: Vec<Daemon>,
+    daemons_checked: Option<Instant>,
+    /// systemd units of fan daemons stopped for the session
+    paused_units: Vec<String>,
+    /// systemctl verb and units, run outside the TUI after the events
+    daemon_request: Option<(&'static str, Vec<String>)>,
+    /// Level last written to all fans, compared with the level read back
+    written_level: Option<(String, Instant)>,
+    /// Last status lines read from the fan file
+    fan_status: Vec<String>,
+    /// Errors of the last reads, the last good readings are kept meanwhile
+    sensors_error: Option<Error>,
+    sensors_backoff: Backoff,
+    fan_error: Option<Error>,
+    fan_backoff: Backoff,
+    /// Times between readings of the sensors and the fan, with the
+    /// threshold in °C
+    polling: PollingConfig,
+    /// Pace of the readings, chosen from the thermal activity
+    pace: Pace,
+    /// Last time temperatures changed quickly or an alert was active
+    busy_at: Instant,
+    /// Whether the terminal has the focus, as told by focus events
+    focused: bool,
+    last_sample: Option<Instant>,
+    /// A request is waiting for the sampler thread
+    sampling: bool,
+    /// Read the fan again as soon as possible, after a command was written
+    fan_resample: bool,
+}
+
+/// Runs `sensors -j`. Lines printed before the JSON, such as warnings of
+/// some drivers, are skipped.
+fn read_sensors() -> Result<String, Error> {
+    let output = Command::new("sensors")
+        .arg("-j")
+        .output()
+        .map_err(|e| match e.kind() {
+            io::ErrorKind::NotFound => Error::SensorsNotFound,
+            _ => Error::SensorsSpawn(e.to_string()),
+        })?;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    // sensors also fails if a single chip cannot be read
+    if !output.status.success() && stdout.trim().is_empty() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(Error::SensorsFailed(stderr.trim().to_string()));
+    }
+    Ok(skip_to_json(&stdout).to_string())
 }
 
-fn parse_adapters(json_str: &str) -> Vec<Adapter> {
-    let json: serde_json::Value =
-        serde_json::from_str(json_str).expect("JSON was not well-formatted");
-    let json_obj = json.as_object().unwrap();
-    let mut adapters = Vec::new();
+/// The text from the first line starting with `{` on
+fn skip_to_json(output: &str) -> &str {
+    let mut offset = 0;
+    for line in output.split_inclusive('\n') {
+        if line.trim_start().starts_with('{') {
+            return &output[offset..];
+        }
+        offset += line.len();
+    }
+    output
+}
 
-    for adapter_name in json_obj.keys() {
-        let mut curr_inputs: Vec<(String, String)> = Vec::new();
-        let adapter = json_obj[adapter_name].as_object().unwrap();
+/// Reads `/proc/acpi/ibm/fan`
+fn read_fan_file() -> Result<String, Error> {
+    let file = File::open(PATH_FAN).map_err(|e| Error::FanOpen(e.to_string()))?;
+    let mut buffer = Vec::new();
+    file.take(4096)
+        .read_to_end(&mut buffer)
+        .map_err(|e| Error::FanRead(e.to_string()))?;
+    String::from_utf8(buffer).map_err(|_| Error::FanFormat("invalid UTF-8"))
+}
 
-        for input_key in adapter.keys() {
-            let input = adapter[input_key].clone();
-            if !input.is_object() {
-                continue;
-            }
+/// The status, speed and level lines of the fan file formatted for the Fan
+/// Info block, and the commands it lists
+fn parse_fan_status(content: &str) -> Result<(Vec<String>, Option<Commands>), Error> {
+    let lines: Vec<&str> = content.lines().collect();
+    if lines.len() < 3 {
+        return Err(Error::FanFormat("fewer than 3 lines"));
+    }
+    let status = lines
+        .iter()
+        .take(3)
+        .map(|line| {
+            let mut split = line.split_whitespace();
+            let name = split.next().unwrap_or_default();
+            format!("{} {:>10}", name, split.next().unwrap_or_default())
+        })
+        .collect();
+    Ok((status, Commands::parse(lines.iter().copied())))
+}
 
-            let input_obj = input.as_object().unwrap();
-            for temp_key in input_obj.keys() {
-                if !temp_key.contains("temp") || !temp_key.contains("input") {
-                    continue;
-                }
+/// A fan command shown in a popup before it is written
+#[derive(Debug, Clone, PartialEq)]
+struct Confirmation {
+    command: String,
+    message: String,
+}
 
-                if input_obj[temp_key] == 0.0 {
-                    continue;
-                }
+/// Seconds after which the driver watchdog sets the fan to auto when no
+/// command was written
+const WATCHDOG_TIMEOUT: u32 = 120;
 
-                curr_inputs.push((input_key.clone(), input_obj[temp_key].to_string()));
-            }
-        }
+/// Time between looking for fan daemons, which runs systemctl
+const DAEMON_CHECK_INTERVAL: Duration = Duration::from_secs(10);
 
-        if curr_inputs.is_empty() {
-            continue;
-        }
+/// Longest time between redraws, e.g. for the revert countdown
+const REDRAW_INTERVAL: Duration = Duration::from_millis(250);
 
-        let mut adapter = Adapter::default();
-        adapter.name = adapter_name.clone();
+/// Time between checks for a snapshot of the sampler thread
+const SNAPSHOT_POLL_INTERVAL: Duration = Duration::from_millis(20);
 
-        for (name, temp) in curr_inputs {
-            let mut input = Input::default();
-            input.name = name;
-            input.temp = temp.trim().parse::<f64>().unwrap_or(-99.0);
-            adapter.inputs.push(input);
-        }
-        adapters.push(adapter);
+impl Default for App {
+    fn default() -> Self {
+        Self::new()
     }
-
-    return adapters;
 }
 
 impl App {
@@ -149,276 +354,1744 @@ impl App {
             show_help: false,
             lines: Vec::new(),
             adapters: Vec::new(),
-            fan_command: "",
+            fan_command: String::new(),
+            fan_commands: None,
+            confirmation: None,
+            watchdog: None,
+            safety: Safety::default(),
+            revert_at: None,
             current_error: String::new(),
+            area: Rect::default(),
             visible_rows: 0,
             scroll_offset: 0,
-            start_row: 0,
-            end_row: 0,
+            page_rows: 0,
+            selected: None,
+            follow_cursor: false,
+            dragging_scrollbar: false,
+            tree: tree::TreeState::default(),
             sorting: Sorting::Temp,
+            model: models::detect(),
+            detail: None,
+            alias_input: None,
+            aliases: HashMap::new(),
+            control_sensor: None,
+            history: HashMap::new(),
+            keymap: Keymap::default(),
+            theme: Theme::default(),
+            glyphs: &glyphs::UNICODE,
+            unit: Unit::default(),
+            precision: 0,
+            alerts: Alerts::default(),
+            events: EventLog::default(),
+            show_log: false,
+            log_scroll: 0,
+            known_sensors: HashSet::new(),
+            fans: Vec::new(),
+            fan_target: None,
+            helper: None,
+            read_only: None,
+            access_problem: None,
+            daemons: Vec::new(),
+            daemons_checked: None,
+            paused_units: Vec::new(),
+            daemon_request: None,
+            written_level: None,
+            fan_status: Vec::new(),
+            sensors_error: None,
+            sensors_backoff: Backoff::default(),
+            fan_error: None,
+            fan_backoff: Backoff::default(),
+            polling: PollingConfig::default(),
+            pace: Pace::Normal,
+            busy_at: Instant::now(),
+            focused: true,
+            last_sample: None,
+            sampling: false,
+            fan_resample: false,
         }
     }
 
-    /// runs the application's main loop until the user quits
-    pub fn run(&mut self, terminal: &mut DefaultTerminal) -> io::Result<()> {
-        while !self.exit {
-            self.read_temperatures();
-            self.read_fan();
-            // Add error if present
-            if !self.current_error.is_empty() {
-                self.lines.push(self.current_error.clone());
+    pub fn from_config(config: &config::Config, args: &cli::Args) -> Result<Self, String> {
+        let ascii = args
+            .ascii
+            .or(config.ascii)
+            .unwrap_or_else(glyphs::detect_ascii_from_env);
+        let precision = config.precision.unwrap_or(0);
+        if precision > MAX_PRECISION {
+            return Err(format!(
+                "precision must be at most {}, got {}",
+                MAX_PRECISION, precision
+            ));
+        }
+        Ok(Self {
+            keymap: Keymap::new(&config.keys)?,
+            theme: theme::find(
+                config.theme.as_deref().unwrap_or("dark"),
+                &config.themes,
+                ColorSupport::from_env(),
+            )?,
+            glyphs: if ascii {
+                &glyphs::ASCII
+            } else {
+                &glyphs::UNICODE
+            },
+            unit: config.unit,
+            precision,
+            alerts: Alerts::new(config.alerts.clone(), config.unit),
+            safety: Safety::new(&config.safety, config.unit)?,
+            polling: {
+                config.polling.validate()?;
+                PollingConfig {
+                    threshold: config.unit.delta_to_celsius(config.polling.threshold),
+                    ..config.polling.clone()
+                }
+            },
+            events: match &config.log_file {
+                Some(path) => {
+                    let path = config::expand_home(path);
+                    EventLog::with_file(&path).map_err(|e| format!("{}: {}", path.display(), e))?
+                }
+                None => EventLog::default(),
+            },
+            ..Self::new()
+        })
+    }
+
+    /// runs the application's main loop until the user quits
+    pub fn run(&mut self, terminal: &mut DefaultTerminal) -> io::Result<()> {
+        let sampler = Sampler::spawn(sampler::sample);
+        while !self.exit {
+            let now = Instant::now();
+            self.request_samples(&sampler, now);
+            let (mut sensors_read, mut fan_read) = (false, false);
+            while let Some(snapshot) = sampler.try_recv() {
+                sensors_read |= snapshot.sensors.is_some();
+                fan_read |= snapshot.fan.is_some();
+                self.apply_snapshot(snapshot, now);
+            }
+
+            self.lines = self.fan_status.clone();
+            self.lines.extend(self.read_warnings(now));
+            if fan_read {
+                self.check_written_level(now);
+            }
+            self.lines.extend(self.daemon_lines());
+            self.lines.extend(self.fan_lines());
+            if let Some(timeout) = self.watchdog {
+                self.lines
+                    .push(format!("{} {:>10}", "watchdog:", format!("{} s", timeout)));
+            }
+            self.check_revert(now);
+            if let Some(revert_at) = self.revert_at {
+                let left = revert_at.saturating_duration_since(now).as_secs();
+                self.lines.push(format!(
+                    "{} {:>10}",
+                    "auto in:",
+                    format!("{}:{:02}", left / 60, left % 60)
+                ));
+            }
+            if sensors_read || fan_read {
+                self.check_alerts(now);
+            }
+            self.update_pace(now);
+            self.lines.push(self.polling_line());
+            if let Some(reason) = &self.read_only {
+                self.lines.push(format!("read-only: {}", reason));
+            }
+            if let Some((_, input)) = self
+                .control_sensor
+                .as_deref()
+                .and_then(|id| self.find_sensor(id))
+            {
+                self.lines.push(format!(
+                    "control: {} {}",
+                    input.display_name(),
+                    self.format_temp(input.temp)
+                ));
+            }
+            // Add error if present
+            if !self.current_error.is_empty() {
+                self.lines.push(self.current_error.clone());
+            }
+
+            // Get terminal size
+            let size = terminal
+                .size()
+                .unwrap_or(ratatui::layout::Size::new(10000, 10000));
+            self.fit_to(Rect::new(0, 0, size.width, size.height));
+
+            terminal.draw(|frame| self.draw(frame))?;
+            self.handle_events()?;
+            if let Some((verb, units)) = self.daemon_request.take() {
+                self.run_systemctl(verb, units);
+            }
+        }
+        Ok(())
+    }
+
+    /// Clamps the scroll offset and the cursor to the rows fitting in a
+    /// terminal of the given size, before it is drawn
+    fn fit_to(&mut self, area: Rect) {
+        self.area = area;
+
+        // Calculate visible_rows for the bottom block
+        self.visible_rows = rows_area(self.block_areas(self.area)[1]).height as usize;
+
+        // Clamp scroll_offset so that the last row ends at the bottom of the panel
+        let rows = tree::build(&self.adapters, &self.tree, &self.sorting);
+        let max_scroll = tree::max_scroll(&rows, self.visible_rows);
+        self.scroll_offset = self.scroll_offset.min(max_scroll);
+
+        // Keep the cursor on a row and scroll it into view after it was moved
+        let selected = self.selected_index(&rows);
+        self.selected = rows.get(selected).map(|row| row.id());
+        if self.follow_cursor {
+            self.scroll_offset = self.scroll_offset.min(selected);
+        }
+        while self.follow_cursor
+            && self.scroll_offset < selected
+            && selected
+                >= self.scroll_offset
+                    + tree::rows_fitting(&rows, self.scroll_offset, self.visible_rows)
+        {
+            self.scroll_offset += 1;
+        }
+        self.follow_cursor = false;
+        self.page_rows = tree::rows_fitting(&rows, self.scroll_offset, self.visible_rows).max(1);
+    }
+
+    /// Asks the sampler thread for the readings that are due
+    fn request_samples(&mut self, sampler: &Sampler, now: Instant) {
+        if self.sampling {
+            return;
+        }
+        let due = self.next_sample().is_none_or(|next| now >= next);
+        let request = Request {
+            sensors: due && self.sensors_backoff.ready(now),
+            fan: (due || self.fan_resample) && self.fan_backoff.ready(now),
+            daemons: self
+                .daemons_checked
+                .is_none_or(|checked| now.duration_since(checked) >= DAEMON_CHECK_INTERVAL),
+        };
+        if due {
+            self.last_sample = Some(now);
+        }
+        if request.daemons {
+            self.daemons_checked = Some(now);
+        }
+        if request != Request::default() {
+            sampler.request(request);
+            self.sampling = true;
+            self.fan_resample = false;
+        }
+    }
+
+    /// Time the next reading is due, `None` before the first one
+    fn next_sample(&self) -> Option<Instant> {
+        self.last_sample
+            .map(|last| last + self.polling.interval(self.pace))
+    }
+
+    /// Speeds readings up while temperatures change quickly or an alert is
+    /// active, slows them down when things are calm or the terminal is not
+    /// focused
+    fn update_pace(&mut self, now: Instant) {
+        let rate = self
+            .history
+            .values()
+            .filter_map(SensorHistory::rate)
+            .fold(0.0, |max: f64, rate| max.max(rate.abs()));
+        let busy = self.alerts.active() || rate >= self.polling.threshold;
+        if busy {
+            self.busy_at = now;
+        }
+        self.pace = Pace::choose(busy, now.duration_since(self.busy_at), self.focused);
+    }
+
+    /// Pace and time between readings for the Fan Info block
+    fn polling_line(&self) -> String {
+        let interval = self.polling.interval(self.pace);
+        let interval = if interval.subsec_millis() == 0 {
+            format!("{} s", interval.as_secs())
+        } else {
+            format!("{} ms", interval.as_millis())
+        };
+        let pace = match self.pace {
+            Pace::Fast => "fast",
+            Pace::Normal => "normal",
+            Pace::Slow => "slow",
+        };
+        format!("{} {:>10}", "polling:", format!("{} {}", pace, interval))
+    }
+
+    fn apply_snapshot(&mut self, snapshot: Snapshot, now: Instant) {
+        self.sampling = false;
+        if let Some(output) = snapshot.sensors {
+            self.update_temperatures(output, snapshot.thermal, now);
+        }
+        if let Some(content) = snapshot.fan {
+            self.update_fan(content, now);
+        }
+        if let Some(daemons) = snapshot.daemons {
+            self.update_daemons(daemons);
+        }
+    }
+
+    /// Runs systemctl while the TUI stays open. Without a terminal polkit
+    /// asks for the password through the agent of the desktop session.
+    fn run_systemctl(&mut self, verb: &'static str, units: Vec<String>) {
+        let output = daemons::systemctl(verb, &units)
+            .stdin(Stdio::null())
+            .output();
+        match output {
+            Ok(output) if output.status.success() => {
+                let message = if verb == "stop" {
+                    self.daemons.retain(|daemon| daemon.unit.is_none());
+                    self.paused_units = units;
+                    format!("Paused {}", self.paused_units.join(", "))
+                } else {
+                    let message = format!("Resumed {}", self.paused_units.join(", "));
+                    self.paused_units.clear();
+                    self.daemons_checked = None;
+                    message
+                };
+                self.events.push(Category::Fan, message);
+            }
+            Ok(output) => {
+                let stderr = String::from_utf8_lossy(&output.stderr);
+                self.set_error(format!(
+                    "systemctl {} failed ({}): {}",
+                    verb,
+                    output.status,
+                    stderr.trim()
+                ));
+            }
+            Err(e) => self.set_error(format!("Failed to run systemctl: {}", e)),
+        }
+    }
+
+    fn draw(&self, frame: &mut Frame) {
+        frame.render_widget(self, frame.area());
+
+        if let Some(id) = &self.detail {
+            self.draw_detail(frame, id);
+        }
+
+        if self.show_log {
+            self.draw_log(frame);
+        }
+
+        if self.show_help {
+            self.draw_help(frame);
+        }
+
+        if let Some(confirmation) = &self.confirmation {
+            self.draw_confirmation(frame, confirmation);
+        }
+
+        if let Some(denied) = &self.access_problem {
+            self.draw_access_problem(frame, denied);
+        }
+
+        if self.glyphs == &glyphs::ASCII {
+            let area = frame.area();
+            glyphs::to_ascii(frame.buffer_mut(), area);
+        }
+    }
+
+    /// Draws the help window listing the active key bindings. Sections are
+    /// spread over several columns when they do not fit the terminal height.
+    fn draw_help(&self, frame: &mut Frame) {
+        let area = frame.area();
+        let sections = self.keymap.help_sections();
+        let key_width = sections
+            .iter()
+            .flat_map(|(_, entries)| entries.iter().map(|(keys, _)| keys.chars().count()))
+            .max()
+            .unwrap_or(0)
+            .max(5);
+
+        // Borders and padding take 4 lines
+        let max_lines = (area.height as usize).saturating_sub(4).max(1);
+        let mut columns: Vec<Vec<Line>> = vec![Vec::new()];
+        for (name, entries) in sections {
+            let column = columns.last_mut().unwrap();
+            if !column.is_empty() && column.len() + 1 + entries.len() + 1 > max_lines {
+                columns.push(Vec::new());
+            }
+            let column = columns.last_mut().unwrap();
+            if !column.is_empty() {
+                column.push(Line::from(""));
+            }
+            // The fan control keys are greyed out in read-only mode
+            let disabled = self.read_only.is_some() && name == keymap::FAN_CONTROL;
+            if disabled {
+                column.push(Line::from(vec![name.bold(), " (read-only)".into()]));
+            } else {
+                column.push(Line::from(name.bold()));
+            }
+            for (keys, description) in entries {
+                let line = Line::from(format!(
+                    "  {:<width$}  {}",
+                    keys,
+                    description,
+                    width = key_width
+                ));
+                column.push(if disabled { line.dim() } else { line });
+            }
+        }
+
+        let column_width = columns
+            .iter()
+            .flatten()
+            .map(|line| line.width())
+            .max()
+            .unwrap_or(0) as u16
+            + 2;
+        let column_height = columns.iter().map(|c| c.len()).max().unwrap_or(0) as u16;
+
+        // Popup size
+        let help_width = std::cmp::min(column_width * columns.len() as u16 + 4, area.width);
+        let help_height = std::cmp::min(column_height + 4, area.height);
+        let help_x = area.x + (area.width.saturating_sub(help_width)) / 2;
+        let help_y = area.y + (area.height.saturating_sub(help_height)) / 2;
+        let help_area = Rect::new(help_x, help_y, help_width, help_height);
+
+        // Clear underlying widgets in that rectangle
+        frame.render_widget(Clear, help_area);
+        frame
+            .buffer_mut()
+            .set_style(help_area, self.theme.text_style());
+
+        let block = Block::bordered()
+            .title(Line::from(" Help ".bold()).centered())
+            .border_set(border::THICK)
+            .border_style(self.theme.border_style())
+            .padding(Padding::uniform(1));
+        let inner = block.inner(help_area);
+        block.render(help_area, frame.buffer_mut());
+
+        // Render the columns
+        let column_areas =
+            Layout::horizontal(vec![Constraint::Length(column_width); columns.len()]).split(inner);
+        for (lines, column_area) in columns.into_iter().zip(column_areas.iter()) {
+            Paragraph::new(Text::from(lines))
+                .alignment(Alignment::Left)
+                .render(*column_area, frame.buffer_mut());
+        }
+    }
+
+    /// Temperature in °C converted to the display unit, without the symbol
+    fn format_value(&self, temp: f64) -> String {
+        format!("{:.*}", self.precision, self.unit.convert(temp))
+    }
+
+    /// Temperature in °C converted to the display unit, e.g. "45°C"
+    fn format_temp(&self, temp: f64) -> String {
+        format!(
+            "{}{}",
+            self.format_value(temp),
+            self.unit.symbol(self.glyphs.degree)
+        )
+    }
+
+    /// Coloured marker showing how hot a temperature is
+    fn severity_marker(&self, temp: f64) -> Span<'static> {
+        Span::styled(
+            self.glyphs.severity[theme::temp_level(temp)],
+            Style::default().fg(self.theme.temp_color(temp)),
+        )
+    }
+
+    /// Keys bound to an action for hints in titles, e.g. "?"
+    fn key_hint(&self, action: Action) -> String {
+        self.keymap
+            .keys(action)
+            .first()
+            .map(|key| key.to_string())
+            .unwrap_or_default()
+    }
+
+    /// updates the application's state based on user input, waits until the
+    /// next redraw or reading
+    fn handle_events(&mut self) -> io::Result<()> {
+        let timeout = match self.next_sample() {
+            // Checks for the snapshot every few milliseconds
+            _ if self.sampling => SNAPSHOT_POLL_INTERVAL,
+            Some(next) => next
+                .saturating_duration_since(Instant::now())
+                .min(REDRAW_INTERVAL),
+            None => Duration::ZERO,
+        };
+        if event::poll(timeout)? {
+            self.handle_event(event::read()?);
+        };
+        while event::poll(Duration::from_secs_f32(0.0))? {
+            self.handle_event(event::read()?);
+        }
+        self.write_command_to_fan();
+        Ok(())
+    }
+
+    fn handle_event(&mut self, event: Event) {
+        match event {
+            Event::Key(key) if key.kind == KeyEventKind::Press => self.handle_key_event(key),
+            Event::Mouse(mouse) => self.handle_mouse_event(mouse),
+            Event::FocusGained => self.focused = true,
+            Event::FocusLost => self.focused = false,
+            _ => {}
+        }
+    }
+
+    fn handle_key_event(&mut self, key_event: KeyEvent) {
+        if self.alias_input.is_some() {
+            self.handle_alias_key_event(key_event);
+            return;
+        }
+
+        if let Some(confirmation) = &self.confirmation {
+            match key_event.code {
+                KeyCode::Char('y') | KeyCode::Enter => {
+                    self.fan_command = confirmation.command.clone();
+                    self.confirmation = None;
+                }
+                KeyCode::Char('n') | KeyCode::Esc => self.confirmation = None,
+                _ => {}
+            }
+            return;
+        }
+
+        if self.access_problem.is_some() {
+            match key_event.code {
+                KeyCode::Char('r') => self.retry_access(),
+                KeyCode::Enter | KeyCode::Esc => self.access_problem = None,
+                _ => {}
+            }
+            return;
+        }
+
+        if let Some(action) = self.keymap.action(key_event) {
+            self.perform(action);
+        }
+    }
+
+    fn perform(&mut self, action: Action) {
+        if self.read_only.is_some() && action.is_fan_control() {
+            return;
+        }
+        if let Some(level) = action.fan_level() {
+            self.request_command(FAN_BUTTONS[level as usize].1);
+            return;
+        }
+
+        // The event log takes the navigation keys while open
+        if self.show_log {
+            let page = self.log_page_height() as isize;
+            match action {
+                Action::CursorUp => return self.scroll_log(1),
+                Action::CursorDown => return self.scroll_log(-1),
+                Action::PageUp => return self.scroll_log(page),
+                Action::PageDown => return self.scroll_log(-page),
+                Action::Top => return self.scroll_log(isize::MAX),
+                Action::Bottom => return self.scroll_log(isize::MIN),
+                Action::Close | Action::ToggleLog => {
+                    self.show_log = false;
+                    return;
+                }
+                _ => {}
+            }
+        }
+
+        // Actions of the detail pane
+        if let Some(id) = self.detail.clone() {
+            match action {
+                Action::Pin => toggle(&mut self.tree.pinned, id),
+                Action::Hide => toggle(&mut self.tree.hidden, id),
+                Action::Rename => {
+                    self.alias_input = Some(self.aliases.get(&id).cloned().unwrap_or_default())
+                }
+                Action::Control => {
+                    if self.control_sensor.as_ref() == Some(&id) {
+                        self.control_sensor = None;
+                    } else {
+                        self.control_sensor = Some(id);
+                    }
+                }
+                Action::Activate => {
+                    // Not reopened on the row under the cursor below
+                    self.detail = None;
+                    return;
+                }
+                _ => {}
+            }
+        }
+
+        match action {
+            Action::ToggleHelp => self.show_help = !self.show_help,
+            Action::ToggleLog => {
+                self.show_log = true;
+                self.log_scroll = 0;
+            }
+            Action::Close => {
+                self.show_help = false;
+                self.detail = None;
+            }
+            Action::Quit => self.exit(),
+            Action::FanFull => self.request_command("level full-speed"),
+            Action::FanAuto => self.request_command("level auto"),
+            Action::FanDisengaged => self.request_command("level disengaged"),
+            Action::FanEnable => self.request_command("enable"),
+            Action::FanDisable => self.request_command("disable"),
+            Action::ToggleWatchdog => {
+                let timeout = match self.fan_commands.as_ref().and_then(|c| c.watchdog) {
+                    _ if self.watchdog.is_some() => 0,
+                    Some(max) => WATCHDOG_TIMEOUT.min(max),
+                    None => WATCHDOG_TIMEOUT,
+                };
+                self.request_command(&format!("watchdog {}", timeout));
+            }
+            Action::SelectFan => self.select_next_fan(),
+            Action::PauseDaemons => self.request_pause(),
+            Action::ToggleUnit => self.unit = self.unit.next(),
+            Action::ToggleSort => match self.sorting {
+                Sorting::Name => self.sorting = Sorting::Temp,
+                Sorting::Temp => self.sorting = Sorting::Name,
+            },
+            Action::CollapseAll => self.toggle_collapse_all(),
+            Action::ToggleHidden => self.tree.show_hidden = !self.tree.show_hidden,
+            Action::CursorDown => self.move_cursor(1),
+            Action::CursorUp => self.move_cursor(-1),
+            Action::PageDown => self.move_cursor(self.page_rows as isize),
+            Action::PageUp => self.move_cursor(-(self.page_rows as isize)),
+            Action::Top => self.move_cursor(isize::MIN),
+            Action::Bottom => self.move_cursor(isize::MAX),
+            Action::Collapse => self.set_selected_collapsed(true),
+            Action::Expand => self.set_selected_collapsed(false),
+            Action::Activate if self.detail.is_none() => self.activate_selected(),
+            _ => {}
+        }
+    }
+
+    /// Sets the command written to the fan, after a confirmation for the
+    /// risky ones. Commands the driver does not list are refused.
+    fn request_command(&mut self, command: &str) {
+        if self.read_only.is_some() {
+            return;
+        }
+        if self
+            .fan_commands
+            .as_ref()
+            .is_some_and(|commands| !commands.supports(command))
+        {
+            self.set_error(format!("The driver does not accept '{}'", command));
+            return;
+        }
+        let unsafe_level = self.guarding_sensor().and_then(|(name, temp)| {
+            let min_level = self.safety.check(command, temp)?;
+            Some(format!(
+                "{} is at {}, the lowest safe level is {}.",
+                name,
+                self.format_temp(temp),
+                min_level
+            ))
+        });
+        let message = match (unsafe_level, command) {
+            (Some(message), _) if self.safety.policy() == Policy::Refuse => {
+                self.set_error(format!("Refused '{}': {}", command, message));
+                return;
+            }
+            (Some(message), _) => message,
+            (None, "level disengaged") => "Disengaged runs the fan as fast as it can without \
+                                          regulation by the firmware, which may wear it out."
+                .to_string(),
+            (None, "disable") => {
+                "Disabling the fan stops it until it is enabled again.".to_string()
+            }
+            (None, _) => {
+                self.fan_command = command.to_string();
+                return;
+            }
+        };
+        self.confirmation = Some(Confirmation {
+            command: command.to_string(),
+            message,
+        });
+    }
+
+    /// Name and temperature of the sensor fan levels are checked against,
+    /// the control sensor if one is chosen, otherwise the hottest one
+    fn guarding_sensor(&self) -> Option<(String, f64)> {
+        match self
+            .control_sensor
+            .as_deref()
+            .and_then(|id| self.find_sensor(id))
+        {
+            Some((_, input)) => Some((input.display_name(), input.temp)),
+            None => self.hottest_sensor(),
+        }
+    }
+
+    /// Name and temperature of the hottest sensor that is not hidden
+    fn hottest_sensor(&self) -> Option<(String, f64)> {
+        self.adapters
+            .iter()
+            .flat_map(|a| a.inputs.iter().map(move |i| (tree::sensor_id(a, i), i)))
+            .filter(|(id, _)| !self.tree.hidden.contains(id))
+            .max_by(|(_, a), (_, b)| a.temp.total_cmp(&b.temp))
+            .map(|(_, input)| (input.display_name(), input.temp))
+    }
+
+    /// Goes back to auto once the time for a manual level is up
+    fn check_revert(&mut self, now: Instant) {
+        if self.revert_at.is_some_and(|at| now >= at) {
+            self.revert_at = None;
+            self.events
+                .push(Category::Fan, "Time for the manual level is up");
+            self.fan_command = "level auto".to_string();
+        }
+    }
+
+    /// Starts or stops the timer going back to auto after a command was
+    /// written
+    fn start_revert_timer(&mut self, command: &str) {
+        if safety::is_manual(command) {
+            self.revert_at = self.safety.revert().map(|revert| Instant::now() + revert);
+        } else if command == "level auto" {
+            self.revert_at = None;
+        }
+    }
+
+    fn handle_alias_key_event(&mut self, key_event: KeyEvent) {
+        let Some(alias) = self.alias_input.as_mut() else {
+            return;
+        };
+        match key_event.code {
+            KeyCode::Char(c) => alias.push(c),
+            KeyCode::Backspace => {
+                alias.pop();
+            }
+            KeyCode::Esc => self.alias_input = None,
+            KeyCode::Enter => {
+                let alias = self.alias_input.take().unwrap_or_default();
+                if let Some(id) = self.detail.clone() {
+                    if alias.trim().is_empty() {
+                        self.aliases.remove(&id);
+                    } else {
+                        self.aliases.insert(id, alias.trim().to_string());
+                    }
+                    self.apply_aliases();
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn handle_mouse_event(&mut self, mouse: MouseEvent) {
+        let areas = self.block_areas(self.area);
+        let position = Position::new(mouse.column, mouse.row);
+        let popup_open = self.show_help
+            || self.detail.is_some()
+            || self.show_log
+            || self.confirmation.is_some()
+            || self.access_problem.is_some();
+
+        match mouse.kind {
+            MouseEventKind::ScrollDown if self.show_log => self.scroll_log(-1),
+            MouseEventKind::ScrollUp if self.show_log => self.scroll_log(1),
+            MouseEventKind::ScrollDown if !popup_open && areas[1].contains(position) => {
+                self.scroll_offset = self.scroll_offset.saturating_add(1)
+            }
+            MouseEventKind::ScrollUp if !popup_open && areas[1].contains(position) => {
+                self.scroll_offset = self.scroll_offset.saturating_sub(1)
+            }
+            MouseEventKind::Down(MouseButton::Left) if !popup_open => {
+                if scrollbar_track(areas[1]).contains(position) {
+                    self.dragging_scrollbar = true;
+                    self.scroll_to_track(areas[1], mouse.row);
+                } else if rows_area(areas[1]).contains(position) {
+                    self.click_row(rows_area(areas[1]), mouse.row);
+                } else if let Some(i) = fan_button_areas(fan_buttons_area(areas[0]))
+                    .iter()
+                    .position(|area| area.contains(position))
+                {
+                    self.request_command(FAN_BUTTONS[i].1);
+                }
+            }
+            MouseEventKind::Drag(MouseButton::Left) if self.dragging_scrollbar => {
+                self.scroll_to_track(areas[1], mouse.row)
+            }
+            MouseEventKind::Up(MouseButton::Left) => self.dragging_scrollbar = false,
+            _ => {}
+        }
+    }
+
+    /// Selects the row at the given line of the Temperatures panel. Clicking a
+    /// header collapses or expands it, clicking the selected input opens its
+    /// details.
+    fn click_row(&mut self, rows_area: Rect, y: u16) {
+        let rows = tree::build(&self.adapters, &self.tree, &self.sorting);
+        let mut line = rows_area.y as usize;
+        for row in rows.iter().skip(self.scroll_offset) {
+            line += row.height();
+            if (y as usize) < line {
+                if matches!(row, tree::TreeRow::Header { .. })
+                    || self.selected.as_ref() == Some(&row.id())
+                {
+                    self.selected = Some(row.id());
+                    self.activate_selected();
+                } else {
+                    self.selected = Some(row.id());
+                }
+                return;
+            }
+        }
+    }
+
+    /// Scrolls to the position of the mouse on the scrollbar track
+    fn scroll_to_track(&mut self, block_area: Rect, y: u16) {
+        let track = scrollbar_track(block_area);
+        let rows = tree::build(&self.adapters, &self.tree, &self.sorting);
+        let max_scroll = tree::max_scroll(&rows, self.visible_rows);
+        let fraction =
+            y.saturating_sub(track.y) as f64 / track.height.saturating_sub(1).max(1) as f64;
+        self.scroll_offset = (fraction.clamp(0.0, 1.0) * max_scroll as f64).round() as usize;
+    }
+
+    /// Asks to stop the systemd units of the running fan daemons, or to
+    /// start them again if they were stopped
+    fn request_pause(&mut self) {
+        if !self.paused_units.is_empty() {
+            self.daemon_request = Some(("start", self.paused_units.clone()));
+            return;
+        }
+        let units: Vec<String> = self
+            .daemons
+            .iter()
+            .filter_map(|daemon| daemon.unit.clone())
+            .collect();
+        match self.daemons.first() {
+            _ if !units.is_empty() => self.daemon_request = Some(("stop", units)),
+            Some(daemon) => self.set_error(format!(
+                "{} is not a systemd service, stop it to control the fan",
+                daemon.name
+            )),
+            None => self.set_error("No fan daemon is running".to_string()),
+        }
+    }
+
+    /// Logs fan daemons that were not running at the last check
+    fn update_daemons(&mut self, daemons: Vec<Daemon>) {
+        for daemon in daemons.iter().filter(|d| !self.daemons.contains(d)) {
+            self.events.push(
+                Category::Alert,
+                format!("{} is running and overrides the fan levels", daemon.name),
+            );
+        }
+        self.daemons = daemons;
+    }
+
+    /// Warning about running fan daemons and the paused ones
+    fn daemon_lines(&self) -> Vec<String> {
+        let mut lines = Vec::new();
+        if !self.daemons.is_empty() {
+            let names: Vec<&str> = self.daemons.iter().map(|d| d.name.as_str()).collect();
+            let hint = if self.daemons.iter().any(|d| d.unit.is_some()) {
+                format!("{} pauses it", self.key_hint(Action::PauseDaemons))
+            } else {
+                "stop it to control the fan".to_string()
+            };
+            lines.push(format!(
+                "warning: {} overrides the fan levels, {}",
+                names.join(", "),
+                hint
+            ));
+        }
+        if !self.paused_units.is_empty() {
+            lines.push(format!("paused: {}", self.paused_units.join(", ")));
+        }
+        lines
+    }
+
+    /// Reports a level read back that differs from the one written last,
+    /// which means another program controls the fan
+    fn check_written_level(&mut self, now: Instant) {
+        let Some((written, written_at)) = &self.written_level else {
+            return;
+        };
+        // The driver watchdog sets the fan to auto by itself
+        if self.watchdog.is_some_and(|timeout| {
+            now.duration_since(*written_at) >= Duration::from_secs(timeout.into())
+        }) {
+            self.written_level = None;
+            return;
+        }
+        let Some(level) = self.fan_level() else {
+            return;
+        };
+        let full_speed = |level: &str| level == "full-speed" || level == "disengaged";
+        if level == written || full_speed(level) && full_speed(written) {
+            return;
+        }
+        let by = match self.daemons.first() {
+            Some(daemon) => daemon.name.clone(),
+            None => "another program".to_string(),
+        };
+        let message = format!(
+            "Level {} was written but {} was read back, probably set by {}",
+            written, level, by
+        );
+        self.written_level = None;
+        self.set_error(message);
+    }
+
+    fn exit(&mut self) {
+        self.exit = true;
+    }
+
+    /// Index of the row under the cursor, the first row if the selected row
+    /// is gone
+    fn selected_index(&self, rows: &[tree::TreeRow]) -> usize {
+        self.selected
+            .as_ref()
+            .and_then(|id| rows.iter().position(|row| &row.id() == id))
+            .unwrap_or(0)
+    }
+
+    fn move_cursor(&mut self, delta: isize) {
+        self.follow_cursor = true;
+        let rows = tree::build(&self.adapters, &self.tree, &self.sorting);
+        if rows.is_empty() {
+            return;
+        }
+        let index = self
+            .selected_index(&rows)
+            .saturating_add_signed(delta)
+            .min(rows.len() - 1);
+        self.selected = Some(rows[index].id());
+    }
+
+    /// Collapses or expands the group under the cursor. Collapsing from an
+    /// input moves the cursor to the header of its group.
+    fn set_selected_collapsed(&mut self, collapsed: bool) {
+        self.follow_cursor = true;
+        let rows = tree::build(&self.adapters, &self.tree, &self.sorting);
+        let index = self.selected_index(&rows);
+        let Some(header) = rows[..(index + 1).min(rows.len())]
+            .iter()
+            .rev()
+            .find(|row| matches!(row, tree::TreeRow::Header { .. }))
+        else {
+            return;
+        };
+        let name = header.id();
+        if collapsed {
+            self.selected = Some(name.clone());
+            self.tree.collapsed.insert(name);
+        } else {
+            self.tree.collapsed.remove(&name);
+        }
+    }
+
+    /// Opens the detail pane of the sensor under the cursor, or collapses or
+    /// expands the group under the cursor
+    fn activate_selected(&mut self) {
+        let rows = tree::build(&self.adapters, &self.tree, &self.sorting);
+        match rows.get(self.selected_index(&rows)) {
+            Some(tree::TreeRow::Header { name, .. }) => {
+                toggle(&mut self.tree.collapsed, name.to_string())
+            }
+            Some(row @ tree::TreeRow::Input { .. }) => self.detail = Some(row.id()),
+            None => {}
+        }
+    }
+
+    /// Collapses all groups, or expands all of them if all are collapsed
+    fn toggle_collapse_all(&mut self) {
+        let rows = tree::build(&self.adapters, &self.tree, &self.sorting);
+        let groups: Vec<String> = rows
+            .iter()
+            .filter(|row| matches!(row, tree::TreeRow::Header { .. }))
+            .map(|row| row.id())
+            .collect();
+        if groups.iter().all(|name| self.tree.collapsed.contains(name)) {
+            self.tree.collapsed.clear();
+        } else {
+            self.tree.collapsed.extend(groups);
+        }
+    }
+
+    /// Splits the screen into the Fan Info and Temperatures blocks
+    fn block_areas(&self, area: Rect) -> Rc<[Rect]> {
+        // Borders, lines and fan buttons
+        Layout::vertical([
+            Constraint::Max(3 + self.lines.len() as u16),
+            Constraint::Min(0),
+        ])
+        .split(area)
+    }
+
+    /// Level of the fan as reported by the driver, e.g. "auto"
+    fn fan_level(&self) -> Option<&str> {
+        self.lines
+            .iter()
+            .find_map(|line| line.strip_prefix("level:"))
+            .map(|level| level.trim())
+    }
+
+    /// Level shown on the fan buttons, of the selected fan if any
+    fn target_level(&self) -> Option<String> {
+        match self.fan_target.and_then(|i| self.fans.get(i)) {
+            Some(fan) => fan.level(),
+            None => self.fan_level().map(str::to_string),
+        }
+    }
+
+    /// Speed of every fan and the fan levels are written to, only shown for
+    /// models with more than one fan
+    fn fan_lines(&self) -> Vec<String> {
+        if self.fans.len() < 2 {
+            return Vec::new();
+        }
+        let mut lines: Vec<String> = self
+            .fans
+            .iter()
+            .map(|fan| format!("{} {:>10}", fan.label.clone() + ":", fan.rpm))
+            .collect();
+        if self.fans.iter().any(|fan| fan.pwm.is_some()) {
+            let target = match self.fan_target.and_then(|i| self.fans.get(i)) {
+                Some(fan) => fan.label.as_str(),
+                None => "all",
+            };
+            lines.push(format!("{} {:>10}", "target:", target));
+        } else {
+            // The driver sets the level of both fans at once
+            lines.push(format!("{} {:>10}", "fans:", "together"));
+        }
+        lines
+    }
+
+    /// Cycles the fan levels are written to through the fans that can be
+    /// controlled on their own and all fans
+    fn select_next_fan(&mut self) {
+        if self.fans.len() < 2 {
+            return;
+        }
+        let start = self.fan_target.map_or(0, |i| i + 1);
+        self.fan_target = (start..self.fans.len()).find(|&i| self.fans[i].pwm.is_some());
+    }
+
+    /// Fan speed in RPM
+    fn fan_speed(&self) -> Option<u32> {
+        self.lines
+            .iter()
+            .find_map(|line| line.strip_prefix("speed:"))
+            .and_then(|speed| speed.trim().parse().ok())
+    }
+
+    /// Checks the visible sensors and the fan against the alert levels and
+    /// sends the resulting events
+    fn check_alerts(&mut self, now: Instant) {
+        let sensors: Vec<(String, String, f64)> = self
+            .adapters
+            .iter()
+            .flat_map(|a| a.inputs.iter().map(move |i| (tree::sensor_id(a, i), i)))
+            .filter(|(id, _)| !self.tree.hidden.contains(id))
+            .map(|(id, input)| (id, input.display_name(), input.temp))
+            .collect();
+        let readings: Vec<Reading> = sensors
+            .iter()
+            .map(|(id, name, temp)| Reading {
+                id,
+                name,
+                temp: *temp,
+            })
+            .collect();
+        let level = self.fan_level().map(str::to_string);
+        let events = self
+            .alerts
+            .update(&readings, level.as_deref(), self.fan_speed(), now);
+        for event in events {
+            let (summary, body) = alerts::describe(&event, &self.format_temp(event.temp));
+            self.events
+                .push(Category::Alert, format!("{}: {}", summary, body));
+            self.alerts
+                .dispatch(&event, &self.format_value(event.temp), self.unit);
+            if event.kind == alerts::Kind::FanFault {
+                self.fall_back();
+            }
+        }
+    }
+
+    /// Sets the fallback level after a fan fault, unless the user already
+    /// chose a level that is about to be written
+    fn fall_back(&mut self) {
+        let Some(command) = self.alerts.fallback_command() else {
+            return;
+        };
+        if self.fan_command.is_empty()
+            && self.fan_level().map(|level| format!("level {}", level)) != Some(command.into())
+        {
+            self.fan_command = command.to_string();
+        }
+    }
+
+    fn find_sensor(&self, id: &str) -> Option<(&Adapter, &Input)> {
+        self.adapters
+            .iter()
+            .flat_map(|a| a.inputs.iter().map(move |i| (a, i)))
+            .find(|(a, i)| tree::sensor_id(a, i) == id)
+    }
+
+    fn apply_aliases(&mut self) {
+        for adapter in &mut self.adapters {
+            let ids: Vec<String> = adapter
+                .inputs
+                .iter()
+                .map(|input| tree::sensor_id(adapter, input))
+                .collect();
+            for (input, id) in adapter.inputs.iter_mut().zip(ids) {
+                if let Some(alias) = self.aliases.get(&id) {
+                    input.label = Some(alias.clone());
+                }
+            }
+        }
+    }
+
+    /// Draws the question whether to write a risky fan command
+    fn draw_confirmation(&self, frame: &mut Frame, confirmation: &Confirmation) {
+        let area = frame.area();
+        let width = std::cmp::min(50, area.width);
+        // Borders and padding take 4 columns
+        let mut lines: Vec<Line> = wrap_words(
+            &confirmation.message,
+            (width as usize).saturating_sub(4).max(1),
+        )
+        .into_iter()
+        .map(Line::from)
+        .collect();
+        lines.push(Line::from(""));
+        lines.push(Line::from(
+            format!("Write '{}'?", confirmation.command).bold(),
+        ));
+
+        let height = std::cmp::min(lines.len() as u16 + 2, area.height);
+        let popup_area = Rect::new(
+            area.x + (area.width - width) / 2,
+            area.y + (area.height - height) / 2,
+            width,
+            height,
+        );
+        frame.render_widget(Clear, popup_area);
+        frame
+            .buffer_mut()
+            .set_style(popup_area, self.theme.text_style());
+
+        let block = Block::bordered()
+            .title(Line::from(" Confirm ".bold()).centered())
+            .title_bottom(Line::from(" y confirm | n cancel ").centered())
+            .border_set(border::THICK)
+            .border_style(Style::default().fg(self.theme.alert))
+            .padding(Padding::horizontal(1));
+        Paragraph::new(Text::from(lines))
+            .block(block)
+            .render(popup_area, frame.buffer_mut());
+    }
+
+    /// Draws the fix-it window explaining why the fan cannot be controlled
+    fn draw_access_problem(&self, frame: &mut Frame, denied: &Denied) {
+        let area = frame.area();
+        let width = std::cmp::min(64, area.width);
+        let mut lines = Vec::new();
+        for step in denied.steps() {
+            if step.starts_with("  ") {
+                // Commands are not wrapped so they can be copied
+                lines.push(Line::from(step.bold()));
+            } else {
+                lines.extend(
+                    wrap_words(&step, (width as usize).saturating_sub(4))
+                        .into_iter()
+                        .map(Line::from),
+                );
             }
+        }
+        lines.push(Line::from(""));
+        lines.push(Line::from(
+            "Temperatures are shown until fan control is possible.",
+        ));
+
+        let height = std::cmp::min(lines.len() as u16 + 2, area.height);
+        let popup_area = Rect::new(
+            area.x + (area.width - width) / 2,
+            area.y + (area.height - height) / 2,
+            width,
+            height,
+        );
+        frame.render_widget(Clear, popup_area);
+        frame
+            .buffer_mut()
+            .set_style(popup_area, self.theme.text_style());
+
+        let block = Block::bordered()
+            .title(Line::from(" Fan control unavailable ".bold()).centered())
+            .title_bottom(Line::from(" r retry | Esc continue read-only ").centered())
+            .border_set(border::THICK)
+            .border_style(Style::default().fg(self.theme.alert))
+            .padding(Padding::horizontal(1));
+        Paragraph::new(Text::from(lines))
+            .block(block)
+            .render(popup_area, frame.buffer_mut());
+    }
 
-            // Get terminal size
-            let terminal_height = terminal
-                .size()
-                .unwrap_or(ratatui::layout::Size::new(10000, 10000))
-                .height as usize;
+    /// Switches to read-only mode and shows the fix-it window
+    fn deny(&mut self, denied: Denied) {
+        self.read_only = Some(denied.reason());
+        self.access_problem = Some(denied);
+    }
+
+    /// Checks the fan file again after the user followed the fix-it steps
+    fn retry_access(&mut self) {
+        match access::check() {
+            Ok(()) => {
+                self.read_only = None;
+                self.access_problem = None;
+                self.events.push(Category::Fan, "Fan control is available");
+            }
+            Err(denied) => self.deny(denied),
+        }
+    }
 
-            // Calculate visible_rows for the bottom block (fan info block height + others)
-            // Subtract borders (2 above, 2 below) and lines. Rounds down to smallest even
-            // number to fit label and bar.
-            self.visible_rows = terminal_height.saturating_sub(2 + 2 + self.lines.len()) & !1;
+    /// Area of the event log window
+    fn log_area(&self, area: Rect) -> Rect {
+        let width = std::cmp::min(100, area.width.saturating_sub(4));
+        let height = area.height.saturating_sub(4);
+        Rect::new(
+            area.x + (area.width - width) / 2,
+            area.y + (area.height - height) / 2,
+            width,
+            height,
+        )
+    }
 
-            // Total rows to scroll through
-            let total_inputs: usize = self.adapters.iter().map(|a| a.inputs.len()).sum();
-            let total_rows = total_inputs * 2;
+    /// Number of entries shown at once in the event log
+    fn log_page_height(&self) -> usize {
+        self.log_area(self.area).height.saturating_sub(2).max(1) as usize
+    }
 
-            // Clamp scroll_offset based on visible rows
-            let max_scroll = total_rows.saturating_sub(self.visible_rows);
-            self.scroll_offset = self.scroll_offset.min(max_scroll) & !1;
+    /// Scrolls the event log up by a number of entries, negative numbers
+    /// scroll down towards the newest entry
+    fn scroll_log(&mut self, delta: isize) {
+        let max_scroll = self
+            .events
+            .entries()
+            .len()
+            .saturating_sub(self.log_page_height());
+        self.log_scroll = self.log_scroll.saturating_add_signed(delta).min(max_scroll);
+    }
 
-            self.start_row = self.scroll_offset;
-            self.end_row = (self.scroll_offset + self.visible_rows).min(total_rows);
+    /// Draws the event log with the newest entries at the bottom
+    fn draw_log(&self, frame: &mut Frame) {
+        let log_area = self.log_area(frame.area());
+        frame.render_widget(Clear, log_area);
+        frame
+            .buffer_mut()
+            .set_style(log_area, self.theme.text_style());
 
-            terminal.draw(|frame| self.draw(frame))?;
-            self.handle_events()?;
+        let block = Block::bordered()
+            .title(Line::from(" Event Log ".bold()).centered())
+            .title_bottom(
+                Line::from(format!(
+                    " {}/{} scroll | {} close ",
+                    self.key_hint(Action::CursorUp),
+                    self.key_hint(Action::CursorDown),
+                    self.key_hint(Action::Close)
+                ))
+                .centered(),
+            )
+            .border_set(border::THICK)
+            .border_style(self.theme.border_style())
+            .padding(Padding::horizontal(1));
+        let inner = block.inner(log_area);
+        block.render(log_area, frame.buffer_mut());
+
+        let entries = self.events.entries();
+        if entries.is_empty() {
+            Paragraph::new("No events yet").render(inner, frame.buffer_mut());
+            return;
         }
-        Ok(())
+        let height = inner.height as usize;
+        let end = entries.len() - self.log_scroll.min(entries.len());
+        let start = end.saturating_sub(height);
+        let lines: Vec<Line> = entries
+            .range(start..end)
+            .map(|entry| {
+                let style = match entry.category {
+                    Category::Error | Category::Alert => Style::default().fg(self.theme.alert),
+                    Category::Fan | Category::Sensor => Style::default(),
+                };
+                Line::from(vec![
+                    Span::raw(format!("{}  ", entry.time)),
+                    Span::styled(format!("{:<6}", entry.category.name()), style),
+                    Span::raw(format!("  {}", entry.message)),
+                ])
+            })
+            .collect();
+        Paragraph::new(Text::from(lines)).render(inner, frame.buffer_mut());
     }
 
-    fn draw(&self, frame: &mut Frame) {
-        frame.render_widget(self, frame.area());
+    fn draw_detail(&self, frame: &mut Frame, id: &str) {
         let area = frame.area();
+        let detail_width = std::cmp::min(72, area.width);
+        let detail_height = std::cmp::min(26, area.height);
+        let detail_x = area.x + (area.width.saturating_sub(detail_width)) / 2;
+        let detail_y = area.y + (area.height.saturating_sub(detail_height)) / 2;
+        let detail_area = Rect::new(detail_x, detail_y, detail_width, detail_height);
+
+        frame.render_widget(Clear, detail_area);
+        frame
+            .buffer_mut()
+            .set_style(detail_area, self.theme.text_style());
+
+        let block = Block::bordered()
+            .title(Line::from(" Sensor ".bold()).centered())
+            .title_bottom(
+                Line::from(format!(
+                    " {} pin | {} hide | {} rename | {} control | {} close ",
+                    self.key_hint(Action::Pin),
+                    self.key_hint(Action::Hide),
+                    self.key_hint(Action::Rename),
+                    self.key_hint(Action::Control),
+                    self.key_hint(Action::Close)
+                ))
+                .centered(),
+            )
+            .border_set(border::THICK)
+            .border_style(self.theme.border_style())
+            .padding(Padding::horizontal(1));
+        let inner = block.inner(detail_area);
+        block.render(detail_area, frame.buffer_mut());
+
+        let Some((adapter, input)) = self.find_sensor(id) else {
+            Paragraph::new("Sensor is no longer available").render(inner, frame.buffer_mut());
+            return;
+        };
+        let history = self.history.get(id);
+        let symbol = self.unit.symbol(self.glyphs.degree);
+        // At least one decimal, the window is for a closer look
+        let precision = self.precision.max(1);
+        let format_temp = |temp: Option<f64>| match temp {
+            Some(temp) => format!("{:.*}{}", precision, self.unit.convert(temp), symbol),
+            None => "-".to_string(),
+        };
 
-        if self.show_help {
-            // Popup size
-            let help_width = std::cmp::min(40, area.width);
-            let help_height = std::cmp::min(18, area.height);
-            let help_x = area.x + (area.width.saturating_sub(help_width)) / 2;
-            let help_y = area.y + (area.height.saturating_sub(help_height)) / 2;
-            let help_area = Rect::new(help_x, help_y, help_width, help_height);
-
-            // Clear underlying widgets in that rectangle
-            frame.render_widget(Clear, help_area);
-
-            // The help text
-            let help_text = Text::from(vec![
-                Line::from("Fan control".bold()),
-                Line::from("  0–7       Specific level"),
-                Line::from("  A         Auto"),
-                Line::from("  F         Full speed"),
-                Line::from(""),
-                Line::from("Temperature".bold()),
-                Line::from("  S         Toggle sorting"),
-                Line::from("  ↑/↓       Scroll up/down"),
-                Line::from("  PgUp/PgDn Scroll page"),
-                Line::from(""),
-                Line::from("Global".bold()),
-                Line::from("  ?         Toggle help window"),
-                Line::from("  Esc       Close help window"),
-                Line::from("  Q         Quit"),
-            ]);
-
-            // Render the popup
-            Paragraph::new(help_text)
-                .alignment(Alignment::Left)
-                .block(
-                    Block::bordered()
-                        .title(Line::from(" Help ".bold()).centered())
-                        .border_set(border::THICK)
-                        .padding(Padding::uniform(1)),
-                )
-                .render(help_area, frame.buffer_mut());
+        let path = hwmon::find_input_path(Path::new(hwmon::PATH_HWMON), &adapter.name, &input.key)
+            .map(|p| p.display().to_string())
+            .unwrap_or_else(|| "unknown".to_string());
+        // Wrap the raw values over as many lines as needed
+        let mut raw_lines = vec![String::new()];
+        for (key, value) in &input.raw {
+            let entry = format!("{}={} ", key, value);
+            let last = raw_lines.last_mut().unwrap();
+            if !last.is_empty() && 10 + last.chars().count() + entry.len() > inner.width as usize {
+                raw_lines.push(entry);
+            } else {
+                last.push_str(&entry);
+            }
+        }
+        let mut flags = Vec::new();
+        if self.tree.pinned.contains(id) {
+            flags.push("pinned");
+        }
+        if self.tree.hidden.contains(id) {
+            flags.push("hidden");
+        }
+        if self.control_sensor.as_deref() == Some(id) {
+            flags.push("control sensor");
         }
-    }
 
-    /// updates the application's state based on user input
-    fn handle_events(&mut self) -> io::Result<()> {
-        let timeout = Duration::from_secs_f32(1.0);
-        if event::poll(timeout)? {
-            if let Event::Key(key) = event::read()? {
-                if key.kind == KeyEventKind::Press {
-                    self.handle_key_event(key)
-                }
-            }
+        let mut lines = vec![
+            Line::from(vec![
+                format!("{} | {}", adapter.name, input.display_name()).bold(),
+                Span::raw(format!("  {}", flags.join(", "))),
+            ]),
+            Line::from(format!("Path:     {}", path)),
+        ];
+        for (i, raw) in raw_lines.into_iter().enumerate() {
+            let title = if i == 0 { "Keys:" } else { "" };
+            lines.push(Line::from(format!("{:<10}{}", title, raw)));
+        }
+        lines.extend([
+            Line::from(format!(
+                "Current:  {}   max {}   crit {}",
+                format_temp(Some(input.temp)),
+                format_temp(input.max()),
+                format_temp(input.crit())
+            )),
+            Line::from(format!(
+                "Session:  min {}   avg {}   max {}",
+                format_temp(history.and_then(|h| h.min())),
+                format_temp(history.and_then(|h| h.avg())),
+                format_temp(history.and_then(|h| h.max()))
+            )),
+            Line::from(match history.and_then(|h| h.rate()) {
+                Some(rate) => format!(
+                    "Rate:     {:+.2}{}/s",
+                    self.unit.convert_delta(rate),
+                    symbol
+                ),
+                None => "Rate:     -".to_string(),
+            }),
+        ]);
+        if let Some(alias) = &self.alias_input {
+            lines.push(Line::from(vec![
+                "Rename:   ".bold(),
+                Span::raw(alias.clone()),
+                Span::raw(self.glyphs.cursor),
+            ]));
+        }
+
+        let areas = Layout::vertical([
+            Constraint::Length(lines.len() as u16 + 1),
+            Constraint::Min(0),
+        ])
+        .split(inner);
+        Paragraph::new(Text::from(lines)).render(areas[0], frame.buffer_mut());
+
+        // History chart
+        let points: Vec<(f64, f64)> = history
+            .map(|h| h.points())
+            .unwrap_or_default()
+            .into_iter()
+            .map(|(time, temp)| (time, self.unit.convert(temp)))
+            .collect();
+        let min_x = points.first().map(|p| p.0).unwrap_or(0.0).min(-1.0);
+        let min_y = points.iter().map(|p| p.1).fold(f64::INFINITY, f64::min);
+        let max_y = points.iter().map(|p| p.1).fold(f64::NEG_INFINITY, f64::max);
+        let (min_y, max_y) = if points.is_empty() {
+            (self.unit.convert(0.0), self.unit.convert(100.0))
+        } else {
+            ((min_y - 2.0).floor(), (max_y + 2.0).ceil())
         };
-        while event::poll(Duration::from_secs_f32(0.0))? {
-            if let Event::Key(key) = event::read()? {
-                if key.kind == KeyEventKind::Press {
-                    self.handle_key_event(key)
+
+        let dataset = Dataset::default()
+            .marker(Marker::Braille)
+            .graph_type(GraphType::Line)
+            .style(Style::default().fg(self.theme.temp_color(input.temp)))
+            .data(&points);
+        Chart::new(vec![dataset])
+            .x_axis(
+                Axis::default()
+                    .bounds([min_x, 0.0])
+                    .labels([format!("{:.0}s", min_x), "now".to_string()]),
+            )
+            .y_axis(
+                Axis::default()
+                    .bounds([min_y, max_y])
+                    .labels([format!("{:.0}", min_y), format!("{:.0}", max_y)]),
+            )
+            .render(areas[1], frame.buffer_mut());
+    }
+
+    /// Keeps the last status lines if the fan file could not be read
+    fn update_fan(&mut self, content: Result<String, Error>, now: Instant) {
+        match content.and_then(|content| parse_fan_status(&content)) {
+            Ok((status, commands)) => {
+                if self.fan_backoff.succeeded() {
+                    self.events
+                        .push(Category::Fan, format!("{} is readable again", PATH_FAN));
                 }
+                self.fan_error = None;
+                self.fan_status = status;
+                self.fan_commands = commands;
             }
+            Err(error) => Self::record_failure(
+                &mut self.fan_backoff,
+                &mut self.fan_error,
+                &mut self.events,
+                error,
+                now,
+            ),
         }
-        self.write_command_to_fan();
-        Ok(())
     }
 
-    fn handle_key_event(&mut self, key_event: KeyEvent) {
-        match key_event.code {
-            KeyCode::Char('?') => self.show_help = !self.show_help,
-            KeyCode::Esc => self.show_help = false,
-            KeyCode::Char('q') => self.exit(),
-            KeyCode::Char('f') => self.fan_command = "level full-speed",
-            KeyCode::Char('a') => self.fan_command = "level auto",
-            KeyCode::Char('0') => self.fan_command = "level 0",
-            KeyCode::Char('1') => self.fan_command = "level 1",
-            KeyCode::Char('2') => self.fan_command = "level 2",
-            KeyCode::Char('3') => self.fan_command = "level 3",
-            KeyCode::Char('4') => self.fan_command = "level 4",
-            KeyCode::Char('5') => self.fan_command = "level 5",
-            KeyCode::Char('6') => self.fan_command = "level 6",
-            KeyCode::Char('7') => self.fan_command = "level 7",
-            KeyCode::Char('s') => match self.sorting {
-                Sorting::Name => self.sorting = Sorting::Temp,
-                Sorting::Temp => self.sorting = Sorting::Name,
-            },
-            KeyCode::Down => self.scroll_offset = self.scroll_offset.saturating_add(2),
-            KeyCode::Up => self.scroll_offset = self.scroll_offset.saturating_sub(2),
-            KeyCode::PageDown => {
-                self.scroll_offset = self.scroll_offset.saturating_add(self.visible_rows)
-            }
-            KeyCode::PageUp => {
-                self.scroll_offset = self.scroll_offset.saturating_sub(self.visible_rows)
-            }
-            _ => {}
+    /// Delays the next read and logs the error unless it is already shown
+    fn record_failure(
+        backoff: &mut Backoff,
+        current: &mut Option<Error>,
+        events: &mut EventLog,
+        error: Error,
+        now: Instant,
+    ) {
+        backoff.failed(now);
+        if current.as_ref() != Some(&error) {
+            events.push(Category::Error, error.to_string());
         }
+        *current = Some(error);
     }
 
-    fn exit(&mut self) {
-        self.exit = true;
+    /// Failing reads and when they are tried again
+    fn read_warnings(&self, now: Instant) -> Vec<String> {
+        [
+            (&self.sensors_error, &self.sensors_backoff),
+            (&self.fan_error, &self.fan_backoff),
+        ]
+        .into_iter()
+        .filter_map(|(error, backoff)| {
+            let retry_in = backoff.retry_in(now).unwrap_or_default();
+            Some(format!(
+                "warning: {}, retrying in {} s",
+                error.as_ref()?,
+                retry_in.as_secs_f64().ceil()
+            ))
+        })
+        .collect()
     }
 
-    fn read_fan(&mut self) {
-        self.lines.clear();
-        let file = match File::open(PATH_FAN) {
-            Ok(f) => f,
-            Err(_) => {
-                self.current_error = "Failed to open file: ".to_string() + PATH_FAN;
-                return;
-            }
-        };
-
-        let mut buffer = [0; 64];
-        let mut handle = file.take(63);
+    /// Shows an error below the fan info and logs it unless it is already
+    /// shown
+    fn set_error(&mut self, error: String) {
+        if error != self.current_error {
+            self.events.push(Category::Error, error.as_str());
+            self.current_error = error;
+        }
+    }
 
-        if let Err(_) = handle.read(&mut buffer) {
-            self.current_error = "Failed to read from file: ".to_string() + PATH_FAN;
+    fn write_command_to_fan(&mut self) {
+        if self.fan_command.is_empty() {
+            return;
+        }
+        if self.read_only.is_some() {
+            self.fan_command.clear();
             return;
         }
 
-        let lines: Vec<&str> = match std::str::from_utf8(&buffer) {
-            Ok(s) => s.lines().collect(),
-            Err(_) => {
-                self.current_error = "Invalid UTF-8 in file: ".to_string() + PATH_FAN;
-                return;
+        // Only levels can be set for a single fan
+        let fan = self.fan_target.and_then(|i| self.fans.get(i));
+        if let Some(fan) = fan.filter(|_| self.fan_command.starts_with("level ")) {
+            match fan.write_command(&self.fan_command, self.helper.as_mut()) {
+                Ok(()) => {
+                    self.current_error = String::new();
+                    self.written_level = None;
+                    self.fan_resample = true;
+                    let message = format!("Wrote '{}' to {}", self.fan_command, fan.label);
+                    self.events.push(Category::Fan, message);
+                    self.start_revert_timer(&self.fan_command.clone());
+                }
+                Err(reason) => self.set_error(
+                    Error::FanWrite {
+                        command: self.fan_command.clone(),
+                        reason,
+                    }
+                    .to_string(),
+                ),
             }
-        };
-
-        if lines.len() < 3 {
-            self.current_error = "Unexpected number of lines in file: ".to_string() + PATH_FAN;
+            self.fan_command.clear();
             return;
         }
 
-        self.lines = lines
+        let result = match &mut self.helper {
+            Some(helper) => helper.write(&self.fan_command),
+            None => std::fs::write(PATH_FAN, &self.fan_command)
+                .map_err(|e| format!("{}: {}", PATH_FAN, e)),
+        };
+        match result {
+            Ok(()) => {
+                self.current_error = String::new();
+                self.fan_resample = true;
+                self.events
+                    .push(Category::Fan, format!("Wrote '{}'", self.fan_command));
+                if let Some(timeout) = self.fan_command.strip_prefix("watchdog ") {
+                    self.watchdog = timeout.parse().ok().filter(|&t| t > 0);
+                } else {
+                    self.written_level = self
+                        .fan_command
+                        .strip_prefix("level ")
+                        .map(|level| (level.to_string(), Instant::now()));
+                }
+                self.start_revert_timer(&self.fan_command.clone());
+            }
+            Err(reason) => self.set_error(
+                Error::FanWrite {
+                    command: self.fan_command.clone(),
+                    reason,
+                }
+                .to_string(),
+            ),
+        }
+
+        self.fan_command.clear();
+    }
+
+    /// Logs sensors that appeared or disappeared since the last reading
+    fn log_sensor_changes(&mut self) {
+        let sensors: HashSet<String> = self
+            .adapters
             .iter()
-            .take(3)
-            .map(|line| {
-                let split: Vec<_> = line.split_whitespace().collect();
-                format!("{} {:>10}", split[0], split[1])
-            })
+            .flat_map(|a| a.inputs.iter().map(move |i| tree::sensor_id(a, i)))
             .collect();
+        if self.known_sensors.is_empty() {
+            self.events
+                .push(Category::Sensor, format!("Found {} sensors", sensors.len()));
+        } else {
+            let mut appeared: Vec<&String> = sensors.difference(&self.known_sensors).collect();
+            let mut disappeared: Vec<&String> = self.known_sensors.difference(&sensors).collect();
+            appeared.sort();
+            disappeared.sort();
+            for id in appeared {
+                self.events
+                    .push(Category::Sensor, format!("{} appeared", id));
+            }
+            for id in disappeared {
+                self.events
+                    .push(Category::Sensor, format!("{} disappeared", id));
+            }
+        }
+        self.known_sensors = sensors;
     }
 
-    fn write_command_to_fan(&mut self) {
-        if self.fan_command.is_empty() {
-            return;
+    /// Keeps the last readings if `sensors` failed, they are shown as stale
+    fn update_temperatures(
+        &mut self,
+        output: Result<String, Error>,
+        thermal: Option<Adapter>,
+        now: Instant,
+    ) {
+        let (mut adapters, json_str) =
+            match output.and_then(|json| Ok((parse_adapters(&json)?, json))) {
+                Ok(result) => result,
+                Err(error) => {
+                    return Self::record_failure(
+                        &mut self.sensors_backoff,
+                        &mut self.sensors_error,
+                        &mut self.events,
+                        error,
+                        now,
+                    )
+                }
+            };
+        if self.sensors_backoff.succeeded() {
+            self.events.push(Category::Sensor, "sensors works again");
+        }
+        self.sensors_error = None;
+        adapters.extend(thermal);
+        self.adapters = adapters;
+        self.fans = fans::parse_fans(&json_str, Path::new(hwmon::PATH_HWMON));
+        if self
+            .fan_target
+            .is_some_and(|i| self.fans.get(i).is_none_or(|fan| fan.pwm.is_none()))
+        {
+            self.fan_target = None;
         }
+        models::apply_sensor_names(self.model, &mut self.adapters);
+        self.apply_aliases();
 
-        match std::fs::write(PATH_FAN, self.fan_command) {
-            Ok(_) => self.current_error = String::new(),
-            Err(_) => {
-                self.current_error =
-                    "Failed to write command '".to_string() + self.fan_command + "' to " + PATH_FAN
+        self.log_sensor_changes();
+
+        for adapter in &self.adapters {
+            for input in &adapter.inputs {
+                self.history
+                    .entry(tree::sensor_id(adapter, input))
+                    .or_default()
+                    .push(now, input.temp);
             }
         }
-
-        self.fan_command = "";
     }
+}
 
-    fn read_temperatures(&mut self) {
-        let output = Command::new("sensors")
-            .arg("-j")
-            .output()
-            .expect("failed to run sensors command");
-        let json_str = std::str::from_utf8(&output.stdout).unwrap();
-        self.adapters = parse_adapters(json_str);
+/// Removes the value from the set if present, otherwise inserts it
+fn toggle(set: &mut std::collections::HashSet<String>, value: String) {
+    if !set.remove(&value) {
+        set.insert(value);
     }
 }
 
-fn fan_control_enabled() -> bool {
-    match std::fs::read_to_string(PATH_MODULE_FAN_CONTROL) {
-        Ok(content) => {
-            let val = content.trim();
-            val == "Y" || val == "1" // some kernels use Y/N, others 1/0
-        }
-        Err(_) => false, // file missing => module not loaded or no permission
+/// Area inside the Temperatures block where the rows are drawn
+fn rows_area(block_area: Rect) -> Rect {
+    let inner_area = Block::bordered().inner(block_area);
+    Rect {
+        x: inner_area.x + 1,
+        y: inner_area.y,
+        width: inner_area.width.saturating_sub(2),
+        height: inner_area.height,
     }
 }
 
-fn update_permissions() -> Result<(), String> {
-    let username = whoami::username();
-
-    let output = Command::new("sudo")
-        .arg("chown")
-        .arg(username)
-        .arg(PATH_FAN)
-        .output()
-        .map_err(|e| format!("Failed to execute sudo: {}", e))?;
+/// Scrollbar track of the Temperatures block, between the arrows
+fn scrollbar_track(block_area: Rect) -> Rect {
+    Rect {
+        x: block_area.x + block_area.width.saturating_sub(1),
+        y: block_area.y + 2,
+        width: 1,
+        // Borders and arrows
+        height: block_area.height.saturating_sub(4),
+    }
+}
 
-    if output.status.success() {
-        Ok(())
-    } else {
-        let stderr = String::from_utf8_lossy(&output.stderr);
-        Err(format!(
-            "chown command failed (exit code {:?}): {}",
-            output.status.code(),
-            stderr.trim()
-        ))
+/// Last line inside the Fan Info block, where the level buttons are drawn
+fn fan_buttons_area(block_area: Rect) -> Rect {
+    let inner_area = Block::bordered().inner(block_area);
+    Rect {
+        y: inner_area.y + inner_area.height.saturating_sub(1),
+        height: inner_area.height.min(1),
+        ..inner_area
     }
 }
 
-fn check_permissions() -> bool {
-    let Ok(f) = File::create(PATH_FAN) else {
-        return false;
-    };
-    let metadata = f.metadata().unwrap();
-    return !metadata.permissions().readonly();
+/// Areas of the fan level buttons, centered on the line
+fn fan_button_areas(line: Rect) -> Vec<Rect> {
+    let widths: Vec<u16> = FAN_BUTTONS
+        .iter()
+        .map(|(label, _)| label.len() as u16 + 2)
+        .collect();
+    let total_width = widths.iter().sum::<u16>() + widths.len() as u16 - 1;
+    let mut x = line.x + line.width.saturating_sub(total_width) / 2;
+    widths
+        .into_iter()
+        .map(|width| {
+            let area = Rect::new(x, line.y, width, line.height).intersection(line);
+            x += width + 1;
+            area
+        })
+        .collect()
+}
+
+/// Splits a text into lines of at most `width` columns at spaces, longer
+/// words get a line of their own
+fn wrap_words(text: &str, width: usize) -> Vec<String> {
+    let mut lines = vec![String::new()];
+    for word in text.split_whitespace() {
+        let last = lines.last_mut().unwrap();
+        if !last.is_empty() && last.chars().count() + 1 + word.chars().count() > width {
+            lines.push(word.to_string());
+        } else {
+            if !last.is_empty() {
+                last.push(' ');
+            }
+            last.push_str(word);
+        }
+    }
+    lines
 }
 
-fn lines_to_text(lines: &Vec<String>) -> Text<'_> {
-    return Text::from(
+fn lines_to_text(lines: &[String]) -> Text<'_> {
+    Text::from(
         lines
             .iter()
             .map(|line| Line::from(line.clone()))
             .collect::<Vec<_>>(),
-    );
+    )
 }
 
 impl Widget for &App {
     fn render(self, area: Rect, buf: &mut Buffer) {
+        buf.set_style(area, self.theme.text_style());
+
         let title_up = Line::from(" Fan Info ".bold());
-        let title_down = Line::from(" Temperatures ".bold());
+        let title_down = match self.sensors_backoff.failing_since() {
+            Some(since) => Line::from(
+                format!(" Temperatures (stale for {} s) ", since.elapsed().as_secs()).bold(),
+            ),
+            None => Line::from(" Temperatures ".bold()),
+        };
         let title_sort = match self.sorting {
             Sorting::Name => Line::from(" Sort by: Name "),
             Sorting::Temp => Line::from(" Sort by: Temp "),
@@ -426,130 +2099,215 @@ impl Widget for &App {
 
         let block_up = Block::bordered()
             .title(title_up.centered())
-            .border_set(border::THICK);
+            .border_set(border::THICK)
+            .border_style(self.theme.border_style());
 
         let block_down = Block::bordered()
             .title(title_down.centered())
             .title_bottom(
-                Line::from(vec![" Press ".into(), "?".bold(), " for help ".into()]).right_aligned(),
+                Line::from(vec![
+                    " Press ".into(),
+                    self.key_hint(Action::ToggleHelp).bold(),
+                    " for help ".into(),
+                ])
+                .right_aligned(),
             )
             .title_bottom(title_sort.left_aligned())
-            .border_set(border::THICK);
-
-        let areas = Layout::vertical([
-            Constraint::Max(2 + self.lines.len() as u16),
-            Constraint::Min(0),
-        ])
-        .split(area);
-
-        // Top info block
-        Paragraph::new(lines_to_text(&self.lines))
+            .border_set(border::THICK)
+            .border_style(self.theme.border_style());
+
+        let areas = self.block_areas(area);
+
+        // Top info block, the error is the last line
+        let mut text = lines_to_text(&self.lines);
+        for line in &mut text.lines {
+            if line
+                .spans
+                .iter()
+                .any(|span| span.content.starts_with("warning:"))
+            {
+                line.style = Style::default().fg(self.theme.alert).bold();
+            }
+        }
+        if !self.current_error.is_empty() {
+            if let Some(line) = text.lines.last_mut() {
+                line.style = Style::default().fg(self.theme.alert);
+            }
+        }
+        Paragraph::new(text)
             .centered()
             .block(block_up)
             .render(areas[0], buf);
 
-        // Layout for adapter content
-        let inner_area = block_down.inner(areas[1]);
-        let padded_area = Rect {
-            x: inner_area.x + 1,
-            y: inner_area.y,
-            width: inner_area.width.saturating_sub(2),
-            height: inner_area.height,
-        };
-
-        // Flatten all adapter inputs
-        let mut rows: Vec<(&str, &str, f64)> = vec![];
-        for adapter in &self.adapters {
-            for input in &adapter.inputs {
-                rows.push((&adapter.name, &input.name, input.temp));
-            }
+        // Fan level buttons, the active level is highlighted
+        let level = self.target_level();
+        let buttons_area = fan_buttons_area(areas[0]);
+        for ((label, command), button_area) in
+            FAN_BUTTONS.iter().zip(fan_button_areas(buttons_area))
+        {
+            let active = match level.as_deref() {
+                Some("full-speed") | Some("disengaged") => *label == "Full",
+                Some(level) => command.strip_prefix("level ") == Some(level),
+                None => false,
+            };
+            let style = if active {
+                Style::default().add_modifier(Modifier::REVERSED)
+            } else {
+                Style::default()
+            };
+            // Greyed out when the fan cannot be controlled
+            let style = if self.read_only.is_some() {
+                style.add_modifier(Modifier::DIM)
+            } else {
+                style
+            };
+            Paragraph::new(Span::styled(format!("[{}]", label), style)).render(button_area, buf);
         }
 
-        // Sort by temperature (descending)
-        match self.sorting {
-            Sorting::Name => {} // already sorted
-            Sorting::Temp => {
-                rows.sort_by(|a, b| b.2.partial_cmp(&a.2).unwrap_or(std::cmp::Ordering::Equal))
-            }
-        };
+        // Layout for adapter content
+        let padded_area = rows_area(areas[1]);
 
-        let total_rows = rows.len() * 2;
-        let visible_rows = padded_area.height as usize & !1;
+        let rows = tree::build(&self.adapters, &self.tree, &self.sorting);
+        let total_rows = tree::total_height(&rows);
+        let visible_rows = padded_area.height as usize;
 
         // Scroll clamping
-        let max_scroll = total_rows.saturating_sub(visible_rows);
-        let start_row = self.scroll_offset.min(max_scroll);
-        let end_row = (start_row + visible_rows).min(total_rows);
-
-        let first_input = start_row / 2;
-        let last_input = (end_row + 1) / 2;
-        let visible_inputs = &rows[first_input..last_input];
-
-        let mut constraints = Vec::with_capacity(visible_inputs.len() * 2);
-        for _ in visible_inputs {
-            constraints.push(Constraint::Length(1));
-            constraints.push(Constraint::Length(1));
+        let start_row = self
+            .scroll_offset
+            .min(tree::max_scroll(&rows, visible_rows));
+        let visible_tree_rows = &rows[start_row..];
+        let visible_count = tree::rows_fitting(&rows, start_row, visible_rows);
+
+        let mut constraints = Vec::with_capacity(visible_count * 2);
+        for row in &visible_tree_rows[..visible_count] {
+            for _ in 0..row.height() {
+                constraints.push(Constraint::Length(1));
+            }
         }
 
         let chunks = Layout::vertical(constraints).split(padded_area);
 
         let show_scrollbar = total_rows > visible_rows;
+        let selected = self.selected_index(&rows);
 
-        // Render visible inputs and bars
+        // Render visible headers, inputs and bars
         let mut i = 0;
-        for (adapter_name, input_name, temp) in visible_inputs {
-            let label = format!("{} | {}", adapter_name, input_name);
-            let fill_ratio = (*temp / 100.0).clamp(0.0, 1.0);
-            let dot_color = match fill_ratio {
-                t if t < 0.45 => GREEN_LIGHT,
-                t if t < 0.75 => YELLOW_LIGHT,
-                _ => RED_LIGHT,
+        for (row_index, row) in visible_tree_rows[..visible_count].iter().enumerate() {
+            let label_style = if start_row + row_index == selected {
+                Style::default().add_modifier(Modifier::REVERSED)
+            } else {
+                Style::default()
             };
-
-            let temp_spans = Line::from(vec![
-                Span::raw(format!("{}°C ", *temp as i8)),
-                Span::styled("▊", Style::default().fg(dot_color)),
-            ]);
-
-            let row_chunks =
-                Layout::horizontal([Constraint::Min(0), Constraint::Length(8)]).split(chunks[i]);
-
-            Paragraph::new(Line::from(Span::raw(label))).render(row_chunks[0], buf);
-            Paragraph::new(temp_spans)
-                .alignment(Alignment::Right)
-                .render(row_chunks[1], buf);
-            i += 1;
-
-            // Render bar
-            let width = chunks[i].width as usize;
-            let filled = (fill_ratio * width as f64).round() as usize;
-
-            let spans: Vec<Span> = (0..width as usize)
-                .map(|idx| {
-                    let ratio = idx as f64 / width as f64;
-                    let color = if idx < filled {
-                        if ratio < 0.45 {
-                            GREEN_LIGHT
-                        } else if ratio < 0.75 {
-                            YELLOW_LIGHT
-                        } else {
-                            RED_LIGHT
-                        }
+            match row {
+                tree::TreeRow::Header {
+                    name,
+                    count,
+                    stats,
+                    collapsed,
+                } => {
+                    let arrow = if *collapsed {
+                        self.glyphs.collapsed
                     } else {
-                        if ratio < 0.45 {
-                            GREEN_DARK
-                        } else if ratio < 0.75 {
-                            YELLOW_DARK
-                        } else {
-                            RED_DARK
-                        }
+                        self.glyphs.expanded
                     };
-                    Span::styled("▀", Style::default().fg(color))
-                })
-                .collect();
-
-            Paragraph::new(Line::from(spans)).render(chunks[i], buf);
-            i += 1;
+                    let label = format!(
+                        "{} {} ({}, hottest: {})",
+                        arrow,
+                        name,
+                        count,
+                        stats.hottest.display_name()
+                    );
+                    let summary = format!(
+                        "min {} avg {} max {} ",
+                        self.format_value(stats.min),
+                        self.format_value(stats.avg),
+                        self.format_temp(stats.max)
+                    );
+                    let summary_width =
+                        (summary.chars().count() + self.glyphs.severity[0].chars().count()) as u16;
+                    let header_spans =
+                        Line::from(vec![Span::raw(summary), self.severity_marker(stats.max)]);
+
+                    let row_chunks =
+                        Layout::horizontal([Constraint::Min(0), Constraint::Length(summary_width)])
+                            .split(chunks[i]);
+
+                    Paragraph::new(Line::from(Span::styled(label, label_style).bold()))
+                        .render(row_chunks[0], buf);
+                    Paragraph::new(header_spans)
+                        .alignment(Alignment::Right)
+                        .render(row_chunks[1], buf);
+                    i += 1;
+                }
+                tree::TreeRow::Input {
+                    adapter,
+                    input,
+                    pinned,
+                    hidden,
+                } => {
+                    let temp = input.temp;
+                    // Pinned inputs are listed away from their adapter
+                    let mut label = if *pinned {
+                        format!("{} | {}", adapter.name, input.display_name())
+                    } else {
+                        input.display_name()
+                    };
+                    if self.control_sensor.as_ref() == Some(&row.id()) {
+                        label += " [control]";
+                    }
+                    let label_style = if *hidden {
+                        label_style.add_modifier(Modifier::DIM)
+                    } else {
+                        label_style
+                    };
+                    let fill_ratio = (temp / 100.0).clamp(0.0, 1.0);
+
+                    let temp_spans = Line::from(vec![
+                        Span::raw(format!("{} ", self.format_temp(temp))),
+                        self.severity_marker(temp),
+                    ]);
+
+                    let row_chunks = Layout::horizontal([
+                        Constraint::Min(0),
+                        Constraint::Length((temp_spans.width() as u16).max(8)),
+                    ])
+                    .split(chunks[i]);
+
+                    Paragraph::new(Line::from(vec![
+                        Span::raw("  "),
+                        Span::styled(label, label_style),
+                    ]))
+                    .render(row_chunks[0], buf);
+                    Paragraph::new(temp_spans)
+                        .alignment(Alignment::Right)
+                        .render(row_chunks[1], buf);
+                    i += 1;
+
+                    // Render bar, indented below the label
+                    let bar_area = Rect {
+                        x: chunks[i].x + 2,
+                        width: chunks[i].width.saturating_sub(2),
+                        ..chunks[i]
+                    };
+                    let width = bar_area.width as usize;
+                    let filled = (fill_ratio * width as f64).round() as usize;
+
+                    let spans: Vec<Span> = (0..width)
+                        .map(|idx| {
+                            let level = theme::level(idx as f64 / width as f64);
+                            let (symbol, color) = if idx < filled {
+                                (self.glyphs.bar, self.theme.bar[level])
+                            } else {
+                                (self.glyphs.bar_empty, self.theme.bar_empty[level])
+                            };
+                            Span::styled(symbol, Style::default().fg(color))
+                        })
+                        .collect();
+
+                    Paragraph::new(Line::from(spans)).render(bar_area, buf);
+                    i += 1;
+                }
+            }
         }
 
         // Draw block border
@@ -558,13 +2316,13 @@ impl Widget for &App {
         // Scrollbar integration
         let content_height = total_rows;
         let visible_height = visible_rows;
-        let scrollbar_track_height = areas[1].height.saturating_sub(2); // inside border (excluding top/bottom)
+        let track = scrollbar_track(areas[1]);
 
-        if show_scrollbar && scrollbar_track_height >= 3 && content_height > visible_height {
-            let scroll_offset = self.scroll_offset.min(content_height - visible_height);
+        if show_scrollbar && track.height >= 1 && content_height > visible_height {
+            let scroll_offset = tree::total_height(&rows[..start_row]);
             let effective_scroll_range = (content_height).max(visible_height);
 
-            let thumb_area_height = scrollbar_track_height.saturating_sub(2); // space for Up and Down
+            let thumb_area_height = track.height;
 
             let thumb_height = ((visible_height as f64 / effective_scroll_range as f64)
                 * thumb_area_height as f64)
@@ -574,34 +2332,35 @@ impl Widget for &App {
             // Calculate max scroll offset and thumb position
             let max_scroll_offset = content_height - visible_height;
             let max_thumb_start = thumb_area_height - thumb_height;
-            let scroll_fraction = scroll_offset as f64 / max_scroll_offset as f64;
+            // Rows are not split, so the last row may end above the bottom
+            let scroll_fraction = (scroll_offset as f64 / max_scroll_offset as f64).min(1.0);
             let thumb_start = (scroll_fraction * max_thumb_start as f64).round() as u16;
 
-            let scrollbar_x = areas[1].x + areas[1].width - 1;
-            let track_top = areas[1].y + 2; // Start just inside the top border
-            let track_bottom = track_top + scrollbar_track_height - 2;
+            let scrollbar_x = track.x;
+            let track_top = track.y;
+            let track_bottom = track.y + track.height;
 
             // Up arrow (1 row above track)
             buf[(scrollbar_x, track_top - 1)]
-                .set_symbol("▲")
-                .set_style(Style::default().fg(Color::Gray));
+                .set_symbol(self.glyphs.scroll_up)
+                .set_style(Style::default().fg(self.theme.scrollbar));
 
             // Down arrow (1 row below track)
             buf[(scrollbar_x, track_bottom)]
-                .set_symbol("▼")
-                .set_style(Style::default().fg(Color::Gray));
+                .set_symbol(self.glyphs.scroll_down)
+                .set_style(Style::default().fg(self.theme.scrollbar));
 
             // Draw scrollbar track with thumb
             for i in 0..thumb_area_height {
                 let symbol = if i >= thumb_start && i < thumb_start + thumb_height {
-                    "█" // thumb
+                    self.glyphs.thumb
                 } else {
-                    "░" // track
+                    self.glyphs.track
                 };
 
                 buf[(scrollbar_x, track_top + i)]
                     .set_symbol(symbol)
-                    .set_style(Style::default().fg(Color::Gray));
+                    .set_style(Style::default().fg(self.theme.scrollbar));
             }
         }
     }
@@ -611,10 +2370,563 @@ impl Widget for &App {
 mod tests {
     use super::*;
 
+    fn mouse_event(kind: MouseEventKind, column: u16, row: u16) -> MouseEvent {
+        MouseEvent {
+            kind,
+            column,
+            row,
+            modifiers: crossterm::event::KeyModifiers::NONE,
+        }
+    }
+
+    /// App showing a fixture of `sensors -j` and a fan at level 2
+    fn fixture_app(name: &str) -> App {
+        let json_str = std::fs::read_to_string(format!("testdata/{}", name)).unwrap();
+        let mut app = App::new();
+        app.adapters = parse_adapters(&json_str).unwrap();
+        app.update_fan(
+            Ok("status:\t\tenabled\nspeed:\t\t2952\nlevel:\t\t2\n".into()),
+            Instant::now(),
+        );
+        app.lines = app.fan_status.clone();
+        app
+    }
+
+    /// Lays the app out for a terminal of the given size and draws it like
+    /// the main loop does, returns the rows of the screen
+    fn screen(app: &mut App, width: u16, height: u16) -> Vec<String> {
+        app.fit_to(Rect::new(0, 0, width, height));
+        let backend = ratatui::backend::TestBackend::new(width, height);
+        let mut terminal = ratatui::Terminal::new(backend).unwrap();
+        terminal.draw(|frame| app.draw(frame)).unwrap();
+        let buffer = terminal.backend().buffer();
+        (0..height)
+            .map(|y| (0..width).map(|x| buffer[(x, y)].symbol()).collect())
+            .collect()
+    }
+
+    /// Compares a screen with `testdata/screens/<name>`. Run the tests with
+    /// `UPDATE_SCREENS=1` to write the files after an intended change.
+    fn assert_screen(name: &str, screen: &[String]) {
+        let path = format!("testdata/screens/{}", name);
+        let actual = screen.join("\n") + "\n";
+        if std::env::var_os("UPDATE_SCREENS").is_some() {
+            std::fs::write(&path, &actual).unwrap();
+        }
+        let expected = std::fs::read_to_string(&path).unwrap();
+        assert!(
+            actual == expected,
+            "screen differs from {}:\n{}",
+            path,
+            actual
+        );
+    }
+
+    #[test]
+    fn screens() {
+        // Fan Info, scrollbar and bars, sorted by temperature
+        let mut app = fixture_app("sensors-t490");
+        assert_screen("t490-80x24", &screen(&mut app, 80, 24));
+
+        // Everything fits, no scrollbar
+        app.perform(Action::ToggleSort);
+        assert_screen("t490-60x50-name", &screen(&mut app, 60, 50));
+
+        // The bars are only told apart by colour with Unicode glyphs
+        app.glyphs = &glyphs::ASCII;
+        assert_screen("t490-70x20-ascii", &screen(&mut app, 70, 20));
+
+        let mut app = fixture_app("sensors-t14s-amd-gen4");
+        assert_screen("t14s-amd-gen4-100x30", &screen(&mut app, 100, 30));
+        app.perform(Action::ToggleHelp);
+        assert_screen("help-100x30", &screen(&mut app, 100, 30));
+        // Sections are spread over columns on short terminals
+        assert_screen("help-100x16", &screen(&mut app, 100, 16));
+
+        // A pending confirmation on a terminal narrower than its borders
+        let mut app = fixture_app("sensors-t490");
+        app.adapters[0].inputs[0].temp = 90.0;
+        app.perform(Action::FanLevel0);
+        assert!(app.confirmation.is_some());
+        assert_screen("confirmation-2x10", &screen(&mut app, 2, 10));
+    }
+
+    #[test]
+    fn scroll_clamping() {
+        let mut app = fixture_app("sensors-t490");
+        let max_scroll = |app: &App| {
+            let rows = tree::build(&app.adapters, &app.tree, &app.sorting);
+            tree::max_scroll(&rows, app.visible_rows)
+        };
+
+        // Scrolled past the end, the last row ends at the bottom
+        app.scroll_offset = usize::MAX;
+        let bottom = screen(&mut app, 80, 24);
+        let max_24 = max_scroll(&app);
+        assert_eq!(app.scroll_offset, max_24);
+        assert_screen("t490-80x24-bottom", &bottom);
+
+        // Growing the terminal scrolls back up
+        let screen_40 = screen(&mut app, 80, 40);
+        assert!(app.scroll_offset < max_24);
+        assert_eq!(app.scroll_offset, max_scroll(&app));
+        assert!(screen_40.iter().all(|row| !row.contains('▲')));
+
+        // The cursor is kept in view when it moves below the panel
+        app.scroll_offset = 0;
+        let small = screen(&mut app, 80, 16);
+        assert!(small[7].ends_with('▲'));
+        for _ in 0..app.page_rows {
+            app.perform(Action::CursorDown);
+        }
+        screen(&mut app, 80, 16);
+        assert!(app.scroll_offset > 0);
+        let rows = tree::build(&app.adapters, &app.tree, &app.sorting);
+        let selected = app.selected_index(&rows);
+        assert!((app.scroll_offset..app.scroll_offset + app.page_rows).contains(&selected));
+
+        // Tiny terminals do not panic
+        screen(&mut app, 20, 5);
+        screen(&mut app, 1, 1);
+    }
+
+    #[test]
+    fn fan_buttons() {
+        let areas = fan_button_areas(Rect::new(0, 4, 60, 1));
+        assert_eq!(areas.len(), FAN_BUTTONS.len());
+        // 8 * "[n]" + "[Auto]" + "[Full]" + 9 spaces = 45 columns, centered
+        assert_eq!(areas[0], Rect::new(7, 4, 3, 1));
+        assert_eq!(areas[8], Rect::new(39, 4, 6, 1));
+        assert_eq!(areas[9], Rect::new(46, 4, 6, 1));
+    }
+
+    #[test]
+    fn temperature_format() {
+        let mut app = App::new();
+        // No wrapping above 127°C and rounding instead of truncating
+        assert_eq!(app.format_temp(130.0), "130°C");
+        assert_eq!(app.format_temp(51.9), "52°C");
+
+        app.perform(Action::ToggleUnit);
+        app.precision = 1;
+        assert_eq!(app.format_temp(51.9), "125.4°F");
+        app.perform(Action::ToggleUnit);
+        assert_eq!(app.format_temp(0.0), "273.1K");
+        app.glyphs = &glyphs::ASCII;
+        app.perform(Action::ToggleUnit);
+        assert_eq!(app.format_value(45.25), "45.2");
+        assert_eq!(app.format_temp(45.0), "45.0C");
+    }
+
+    #[test]
+    fn ascii_rendering() {
+        let json_str: String = std::fs::read_to_string("testdata/sensors-t490").unwrap();
+        let mut app = App::new();
+        app.glyphs = &glyphs::ASCII;
+        app.adapters = parse_adapters(json_str.as_str()).unwrap();
+        app.lines = vec!["status: enabled".into(), "level: auto".into()];
+        app.detail = Some("coretemp-isa-0000/Core 2".into());
+        app.history
+            .entry("coretemp-isa-0000/Core 2".into())
+            .or_default()
+            .push(Instant::now(), 51.0);
+
+        let backend = ratatui::backend::TestBackend::new(80, 40);
+        let mut terminal = ratatui::Terminal::new(backend).unwrap();
+        terminal.draw(|frame| app.draw(frame)).unwrap();
+        let buffer = terminal.backend().buffer();
+        assert!(buffer.content.iter().all(|cell| cell.symbol().is_ascii()));
+
+        app.detail = None;
+        terminal.draw(|frame| app.draw(frame)).unwrap();
+        let buffer = terminal.backend().buffer();
+        let line = |y: u16| -> String { (0..80).map(|x| buffer[(x, y)].symbol()).collect() };
+        assert!(line(6).starts_with("| - iwlwifi_1-virtual-0 (1, hottest: temp1) "));
+        assert!(line(6).ends_with("max 54C warm |"));
+        assert!(line(8).starts_with("|   ########################################....."));
+    }
+
+    #[test]
+    fn detail_pane_toggle() {
+        let mut app = fixture_app("sensors-t490");
+        app.fit_to(Rect::new(0, 0, 80, 24));
+        app.perform(Action::CursorDown);
+        app.perform(Action::Activate);
+        let id = app.detail.clone().unwrap();
+        assert_eq!(app.selected.as_deref(), Some(id.as_str()));
+        app.perform(Action::Activate);
+        assert_eq!(app.detail, None);
+    }
+
+    #[test]
+    fn mouse_click() {
+        let json_str: String = std::fs::read_to_string("testdata/sensors-t490").unwrap();
+        let mut app = App::new();
+        app.adapters = parse_adapters(json_str.as_str()).unwrap();
+        app.lines = vec![
+            "status: enabled".into(),
+            "speed: 2000".into(),
+            "level: auto".into(),
+        ];
+        app.area = Rect::new(0, 0, 60, 30);
+        app.visible_rows = 30 - 6 - 2;
+
+        // Fan Info block takes 6 lines, buttons are on the last line inside it
+        app.handle_mouse_event(mouse_event(MouseEventKind::Down(MouseButton::Left), 40, 4));
+        assert_eq!(app.fan_command, "level auto");
+
+        // Header of the hottest adapter, then its first input
+        app.handle_mouse_event(mouse_event(MouseEventKind::Down(MouseButton::Left), 5, 7));
+        assert_eq!(app.selected.as_deref(), Some("iwlwifi_1-virtual-0"));
+        assert!(app.tree.collapsed.contains("iwlwifi_1-virtual-0"));
+        app.handle_mouse_event(mouse_event(MouseEventKind::Down(MouseButton::Left), 5, 9));
+        assert_eq!(app.selected.as_deref(), Some("coretemp-isa-0000/Core 2"));
+        assert_eq!(app.detail, None);
+        app.handle_mouse_event(mouse_event(MouseEventKind::Down(MouseButton::Left), 5, 10));
+        assert_eq!(app.detail.as_deref(), Some("coretemp-isa-0000/Core 2"));
+
+        // Scrolling is ignored while the detail pane is open
+        app.handle_mouse_event(mouse_event(MouseEventKind::ScrollDown, 5, 10));
+        assert_eq!(app.scroll_offset, 0);
+        app.detail = None;
+        app.handle_mouse_event(mouse_event(MouseEventKind::ScrollDown, 5, 10));
+        assert_eq!(app.scroll_offset, 1);
+
+        // Dragging the scrollbar to the bottom of the track
+        app.handle_mouse_event(mouse_event(MouseEventKind::Down(MouseButton::Left), 59, 8));
+        assert!(app.dragging_scrollbar);
+        app.handle_mouse_event(mouse_event(MouseEventKind::Drag(MouseButton::Left), 59, 27));
+        let rows = tree::build(&app.adapters, &app.tree, &app.sorting);
+        assert_eq!(app.scroll_offset, tree::max_scroll(&rows, app.visible_rows));
+        app.handle_mouse_event(mouse_event(MouseEventKind::Up(MouseButton::Left), 59, 27));
+        assert!(!app.dragging_scrollbar);
+    }
+
+    #[test]
+    fn event_log() {
+        let json_str: String = std::fs::read_to_string("testdata/sensors-t490").unwrap();
+        let mut app = App::new();
+        app.area = Rect::new(0, 0, 80, 14);
+        app.adapters = parse_adapters(json_str.as_str()).unwrap();
+        app.log_sensor_changes();
+        app.adapters.pop();
+        app.log_sensor_changes();
+        let messages: Vec<&str> = app
+            .events
+            .entries()
+            .iter()
+            .map(|e| e.message.as_str())
+            .collect();
+        assert_eq!(messages[0], "Found 13 sensors");
+        assert!(messages[1].ends_with(" disappeared"));
+
+        // The same error is only logged once
+        let count = app.events.entries().len();
+        app.set_error("Failed to open file: /proc/acpi/ibm/fan".into());
+        app.set_error("Failed to open file: /proc/acpi/ibm/fan".into());
+        assert_eq!(app.events.entries().len(), count + 1);
+        assert_eq!(app.events.entries()[count].category, Category::Error);
+
+        for i in 0..23 - count - 1 {
+            app.events
+                .push(Category::Fan, format!("Wrote 'level {}'", i % 8));
+        }
+        app.perform(Action::ToggleLog);
+        assert!(app.show_log);
+        // 14 lines minus margins and borders leave 8 entries per page
+        app.perform(Action::PageUp);
+        assert_eq!(app.log_scroll, 8);
+        app.perform(Action::Top);
+        assert_eq!(app.log_scroll, 23 - 8);
+        app.handle_mouse_event(mouse_event(MouseEventKind::ScrollDown, 5, 5));
+        assert_eq!(app.log_scroll, 23 - 9);
+        app.perform(Action::Close);
+        assert!(!app.show_log);
+    }
+
+    #[test]
+    fn fan_fault_fallback() {
+        let config = config::parse(
+            "[alerts]\nbell = false\nnotify = false\nfan_fault = true\nfan_response = 0\nfan_fallback = \"auto\"",
+        )
+        .unwrap();
+        let mut app = App::from_config(&config, &cli::Args::default()).unwrap();
+        app.lines = vec![
+            "status:      enabled".into(),
+            "speed:            0".into(),
+            "level:            7".into(),
+        ];
+        app.check_alerts(Instant::now());
+        assert_eq!(app.fan_command, "level auto");
+        let entry = app.events.entries().back().unwrap();
+        assert_eq!(entry.category, Category::Alert);
+        assert_eq!(
+            entry.message,
+            "Probable fan fault: 0 RPM at a running level"
+        );
+    }
+
+    #[test]
+    fn dual_fans() {
+        let fan = |index, pwm: Option<&str>| fans::Fan {
+            label: format!("fan{}", index),
+            index,
+            rpm: 3000,
+            pwm: pwm.map(std::path::PathBuf::from),
+        };
+        let mut app = App::new();
+        app.fans = vec![fan(1, None)];
+        assert!(app.fan_lines().is_empty());
+        app.perform(Action::SelectFan);
+        assert_eq!(app.fan_target, None);
+
+        app.fans = vec![fan(1, None), fan(2, None)];
+        assert_eq!(
+            app.fan_lines(),
+            vec!["fan1:       3000", "fan2:       3000", "fans:   together"]
+        );
+        app.perform(Action::SelectFan);
+        assert_eq!(app.fan_target, None);
+
+        app.fans = vec![fan(1, Some("/pwm1")), fan(2, Some("/pwm2"))];
+        assert_eq!(app.fan_lines()[2], "target:        all");
+        app.perform(Action::SelectFan);
+        app.perform(Action::SelectFan);
+        assert_eq!(app.fan_target, Some(1));
+        assert_eq!(app.fan_lines()[2], "target:       fan2");
+        app.perform(Action::SelectFan);
+        assert_eq!(app.fan_target, None);
+    }
+
+    #[test]
+    fn fan_commands() {
+        let key = |c| KeyEvent::new(KeyCode::Char(c), crossterm::event::KeyModifiers::NONE);
+        let mut app = App::new();
+        app.fan_commands = Commands::parse(["commands:\tlevel <level> (<level> is 0-7, auto)"]);
+        app.perform(Action::FanDisengaged);
+        assert_eq!(app.fan_command, "");
+        assert_eq!(
+            app.current_error,
+            "The driver does not accept 'level disengaged'"
+        );
+
+        app.fan_commands = Commands::parse([
+            "commands:\tlevel <level> (<level> is 0-7, auto, disengaged, full-speed)",
+            "commands:\tenable, disable",
+            "commands:\twatchdog <timeout> (<timeout> is 0 (off), 1-120 (seconds))",
+        ]);
+        app.perform(Action::FanDisengaged);
+        assert_eq!(app.fan_command, "");
+        assert!(app.confirmation.is_some());
+        // Other keys are ignored while asking
+        app.handle_key_event(key('q'));
+        assert!(!app.exit);
+        app.handle_key_event(key('n'));
+        assert_eq!(app.confirmation, None);
+        assert_eq!(app.fan_command, "");
+
+        app.perform(Action::FanDisable);
+        app.handle_key_event(key('y'));
+        assert_eq!(app.fan_command, "disable");
+        app.perform(Action::FanEnable);
+        assert_eq!(app.fan_command, "enable");
+        app.perform(Action::ToggleWatchdog);
+        assert_eq!(app.fan_command, "watchdog 120");
+        app.watchdog = Some(120);
+        app.perform(Action::ToggleWatchdog);
+        assert_eq!(app.fan_command, "watchdog 0");
+    }
+
+    #[test]
+    fn safety_guard() {
+        let json_str: String = std::fs::read_to_string("testdata/sensors-t490").unwrap();
+        let mut app = App::new();
+        app.adapters = parse_adapters(json_str.as_str()).unwrap();
+        app.perform(Action::FanLevel0);
+        assert_eq!(app.fan_command, "level 0");
+        app.fan_command.clear();
+
+        app.adapters[0].inputs[0].temp = 90.0;
+        let name = app.adapters[0].inputs[0].display_name();
+        app.perform(Action::FanLevel2);
+        let confirmation = app.confirmation.take().unwrap();
+        assert_eq!(confirmation.command, "level 2");
+        assert_eq!(
+            confirmation.message,
+            format!("{} is at 90°C, the lowest safe level is 3.", name)
+        );
+        app.perform(Action::FanLevel3);
+        assert_eq!(app.fan_command, "level 3");
+        app.fan_command.clear();
+
+        // Only the control sensor counts once one is chosen
+        app.control_sensor = Some(tree::sensor_id(
+            &app.adapters[1],
+            &app.adapters[1].inputs[0],
+        ));
+        app.perform(Action::FanLevel2);
+        assert_eq!(app.confirmation, None);
+        assert_eq!(app.fan_command, "level 2");
+        app.fan_command.clear();
+        app.control_sensor = None;
+
+        let config = config::parse("[safety]\npolicy = \"refuse\"\nrevert = 15").unwrap();
+        let mut app = App {
+            adapters: app.adapters,
+            ..App::from_config(&config, &cli::Args::default()).unwrap()
+        };
+        app.perform(Action::FanLevel0);
+        assert_eq!(app.confirmation, None);
+        assert_eq!(app.fan_command, "");
+        assert!(app.current_error.starts_with("Refused 'level 0': "));
+
+        // Manual levels go back to auto after 15 minutes
+        app.start_revert_timer("level 5");
+        let revert_at = app.revert_at.unwrap();
+        assert!(revert_at > Instant::now() + Duration::from_secs(14 * 60));
+        app.check_revert(revert_at - Duration::from_secs(1));
+        assert_eq!(app.fan_command, "");
+        app.check_revert(revert_at);
+        assert_eq!(app.fan_command, "level auto");
+        assert_eq!(app.revert_at, None);
+        app.start_revert_timer("level 5");
+        app.start_revert_timer("level auto");
+        assert_eq!(app.revert_at, None);
+    }
+
+    #[test]
+    fn fan_daemons() {
+        let mut app = App::new();
+        app.lines = vec!["level: auto".into()];
+        app.perform(Action::PauseDaemons);
+        assert_eq!(app.current_error, "No fan daemon is running");
+
+        app.update_daemons(vec![Daemon {
+            name: "thinkfan".into(),
+            unit: Some("thinkfan.service".into()),
+        }]);
+        assert_eq!(
+            app.daemon_lines(),
+            vec!["warning: thinkfan overrides the fan levels, P pauses it"]
+        );
+        let entry = app.events.entries().back().unwrap();
+        assert_eq!(
+            entry.message,
+            "thinkfan is running and overrides the fan levels"
+        );
+        app.perform(Action::PauseDaemons);
+        assert_eq!(
+            app.daemon_request,
+            Some(("stop", vec!["thinkfan.service".to_string()]))
+        );
+        app.daemon_request = None;
+        app.paused_units = vec!["thinkfan.service".into()];
+        app.daemons.clear();
+        assert_eq!(app.daemon_lines(), vec!["paused: thinkfan.service"]);
+        app.perform(Action::PauseDaemons);
+        assert_eq!(app.daemon_request.take().unwrap().0, "start");
+
+        // The level read back differs from the one written
+        let now = Instant::now();
+        app.written_level = Some(("full-speed".into(), now));
+        app.lines = vec!["level: disengaged".into()];
+        app.check_written_level(now);
+        assert!(app.written_level.is_some());
+        app.lines = vec!["level: auto".into()];
+        app.check_written_level(now);
+        assert_eq!(app.written_level, None);
+        assert_eq!(
+            app.current_error,
+            "Level full-speed was written but auto was read back, probably set by another program"
+        );
+
+        // Unless the watchdog set the fan to auto
+        app.current_error.clear();
+        app.watchdog = Some(120);
+        app.written_level = Some(("3".into(), now));
+        app.check_written_level(now + Duration::from_secs(120));
+        assert_eq!(app.written_level, None);
+        assert_eq!(app.current_error, "");
+    }
+
+    #[test]
+    fn access_problem() {
+        let mut app = App::new();
+        app.deny(Denied::FanControlOff { lockdown: None });
+        assert_eq!(
+            app.read_only.as_deref(),
+            Some("thinkpad_acpi fan_control=1 is not set")
+        );
+
+        let backend = ratatui::backend::TestBackend::new(80, 24);
+        let mut terminal = ratatui::Terminal::new(backend).unwrap();
+        terminal.draw(|frame| app.draw(frame)).unwrap();
+        let buffer = terminal.backend().buffer();
+        let line = |y: u16| -> String { (0..80).map(|x| buffer[(x, y)].symbol()).collect() };
+        let screen: Vec<String> = (0..24).map(line).collect();
+        assert!(screen
+            .iter()
+            .any(|l| l.contains(" Fan control unavailable ")));
+        assert!(screen
+            .iter()
+            .any(|l| l.contains("  sudo modprobe thinkpad_acpi fan_control=1 ")));
+
+        // Keys go to the window until it is closed
+        app.handle_key_event(KeyEvent::from(KeyCode::Char('q')));
+        assert!(!app.exit);
+        app.handle_key_event(KeyEvent::from(KeyCode::Esc));
+        assert_eq!(app.access_problem, None);
+        assert!(app.read_only.is_some());
+    }
+
+    #[test]
+    fn read_only() {
+        let mut app = App::new();
+        app.lines = vec![
+            "status: enabled".into(),
+            "speed: 2000".into(),
+            "level: auto".into(),
+        ];
+        app.read_only = Some("started with --read-only".into());
+        app.area = Rect::new(0, 0, 80, 30);
+
+        app.perform(Action::FanFull);
+        app.perform(Action::ToggleWatchdog);
+        app.handle_mouse_event(mouse_event(MouseEventKind::Down(MouseButton::Left), 45, 4));
+        assert_eq!(app.fan_command, "");
+        assert_eq!(app.confirmation, None);
+        app.fan_command = "level 7".into();
+        app.write_command_to_fan();
+        assert_eq!(app.fan_command, "");
+        assert!(app.events.entries().is_empty());
+
+        // Buttons and the fan keys in the help are greyed out
+        let backend = ratatui::backend::TestBackend::new(80, 30);
+        let mut terminal = ratatui::Terminal::new(backend).unwrap();
+        app.perform(Action::ToggleHelp);
+        terminal.draw(|frame| app.draw(frame)).unwrap();
+        let buffer = terminal.backend().buffer();
+        let line = |y: u16| -> String { (0..80).map(|x| buffer[(x, y)].symbol()).collect() };
+        let y = (0..30).find(|&y| line(y).contains("Fan control")).unwrap();
+        assert!(line(y).contains("Fan control (read-only)"));
+        let x = (0..79)
+            .find(|&x| buffer[(x, y + 1)].symbol() == "0" && buffer[(x + 1, y + 1)].symbol() == "–")
+            .unwrap();
+        assert!(buffer[(x, y + 1)].modifier.contains(Modifier::DIM));
+
+        app.perform(Action::ToggleHelp);
+        terminal.draw(|frame| app.draw(frame)).unwrap();
+        let buffer = terminal.backend().buffer();
+        let button = fan_button_areas(fan_buttons_area(app.block_areas(app.area)[0]))[0];
+        assert!(buffer[(button.x, button.y)]
+            .modifier
+            .contains(Modifier::DIM));
+    }
+
     #[test]
     fn sensors_t14s_amd_gen1() {
         let json_str: String = std::fs::read_to_string("testdata/sensors-t14s-amd-gen1").unwrap();
-        let adapters = parse_adapters(json_str.as_str());
+        let adapters = parse_adapters(json_str.as_str()).unwrap();
         assert_eq!(adapters.len(), 7);
 
         assert_eq!(adapters[0].name, "acpitz-acpi-0");
@@ -653,7 +2965,7 @@ mod tests {
     #[test]
     fn sensors_t14s_amd_gen4() {
         let json_str: String = std::fs::read_to_string("testdata/sensors-t14s-amd-gen4").unwrap();
-        let adapters = parse_adapters(json_str.as_str());
+        let adapters = parse_adapters(json_str.as_str()).unwrap();
         assert_eq!(adapters.len(), 6);
 
         assert_eq!(adapters[0].name, "acpitz-acpi-0");
@@ -691,10 +3003,208 @@ mod tests {
         assert_eq!(adapters[5].inputs[3].temp, 45.0);
     }
 
+    #[test]
+    fn sensor_failures() {
+        let json_str: String = std::fs::read_to_string("testdata/sensors-t490").unwrap();
+        let mut app = App::new();
+        let now = Instant::now();
+        app.update_temperatures(Ok(json_str.clone()), None, now);
+        let count = app.adapters.len();
+
+        // The last readings are kept and marked as stale until sensors works
+        app.update_temperatures(Err(Error::SensorsNotFound), None, now);
+        app.update_temperatures(Ok("{".into()), None, now + Duration::from_secs(1));
+        assert_eq!(app.adapters.len(), count);
+        assert!(matches!(app.sensors_error, Some(Error::SensorsOutput(_))));
+        assert!(!app.sensors_backoff.ready(now + Duration::from_secs(2)));
+        assert_eq!(
+            app.read_warnings(now + Duration::from_secs(1))[0],
+            "warning: Invalid output of sensors -j: EOF while parsing an object \
+             at line 1 column 1, retrying in 2 s"
+        );
+        let backend = ratatui::backend::TestBackend::new(80, 20);
+        let mut terminal = ratatui::Terminal::new(backend).unwrap();
+        terminal.draw(|frame| app.draw(frame)).unwrap();
+        let buffer = terminal.backend().buffer();
+        let line: String = (0..80).map(|x| buffer[(x, 3)].symbol()).collect();
+        assert!(line.contains(" Temperatures (stale for 0 s) "));
+
+        app.update_temperatures(Ok(json_str), None, now + Duration::from_secs(3));
+        assert_eq!(app.sensors_error, None);
+        assert!(app.read_warnings(now).is_empty());
+        let messages: Vec<&str> = app
+            .events
+            .entries()
+            .iter()
+            .map(|e| e.message.as_str())
+            .collect();
+        assert_eq!(messages[1], "sensors is not installed, install lm-sensors");
+        assert_eq!(messages.last(), Some(&"sensors works again"));
+
+        // Garbage instead of the fan status
+        app.update_fan(Ok("level: auto".into()), now);
+        assert!(matches!(app.fan_error, Some(Error::FanFormat(_))));
+        app.update_fan(Ok("status:\nspeed:\t\t2000\nlevel:\t\tauto\n".into()), now);
+        assert_eq!(app.fan_error, None);
+        assert_eq!(app.fan_status[0], "status:           ");
+    }
+
+    #[test]
+    fn sampling() {
+        let json_str: String = std::fs::read_to_string("testdata/sensors-t490").unwrap();
+        let (sender, requests) = std::sync::mpsc::channel();
+        let sampler = Sampler::spawn(move |request| {
+            sender.send(request).unwrap();
+            Snapshot {
+                sensors: request.sensors.then(|| Ok(json_str.clone())),
+                thermal: None,
+                fan: request
+                    .fan
+                    .then(|| Ok("status:\tenabled\nspeed:\t2000\nlevel:\tauto\n".into())),
+                daemons: request.daemons.then(|| {
+                    vec![Daemon {
+                        name: "thinkfan".into(),
+                        unit: Some("thinkfan.service".into()),
+                    }]
+                }),
+            }
+        });
+        let mut app = App::new();
+        let now = Instant::now();
+        app.request_samples(&sampler, now);
+        assert_eq!(
+            requests.recv_timeout(Duration::from_secs(5)),
+            Ok(Request {
+                sensors: true,
+                fan: true,
+                daemons: true
+            })
+        );
+        // Nothing is requested while a snapshot is on its way
+        app.request_samples(&sampler, now + Duration::from_secs(1));
+        assert!(app.sampling);
+
+        let snapshot = loop {
+            if let Some(snapshot) = sampler.try_recv() {
+                break snapshot;
+            }
+            std::thread::sleep(Duration::from_millis(1));
+        };
+        app.apply_snapshot(snapshot, now);
+        assert!(!app.sampling);
+        assert_eq!(app.adapters.len(), 6);
+        assert_eq!(app.fan_status[2], "level:       auto");
+        assert_eq!(app.daemons.len(), 1);
+
+        // Only the fan is read again right after a command was written
+        app.fan_resample = true;
+        app.request_samples(&sampler, now + Duration::from_millis(10));
+        assert_eq!(
+            requests.recv_timeout(Duration::from_secs(5)),
+            Ok(Request {
+                sensors: false,
+                fan: true,
+                daemons: false
+            })
+        );
+    }
+
+    #[test]
+    fn adaptive_polling() {
+        let mut app = App::new();
+        let start = Instant::now();
+        let at = |secs| start + Duration::from_secs(secs);
+        app.last_sample = Some(start);
+        app.update_pace(start);
+        assert_eq!(app.pace, Pace::Normal);
+        assert_eq!(app.next_sample(), Some(at(1)));
+
+        // Heating up by 2°C per second
+        let history = app
+            .history
+            .entry("coretemp-isa-0000/Core 0".into())
+            .or_default();
+        history.push(at(0), 50.0);
+        history.push(at(1), 52.0);
+        app.update_pace(at(1));
+        assert_eq!(app.pace, Pace::Fast);
+        assert_eq!(app.next_sample(), Some(start + Duration::from_millis(250)));
+        assert_eq!(app.polling_line(), "polling: fast 250 ms");
+
+        // Calm again, readings slow down after a while
+        let history = app.history.get_mut("coretemp-isa-0000/Core 0").unwrap();
+        for secs in 2..=20 {
+            history.push(at(secs), 52.0);
+        }
+        app.update_pace(at(20));
+        assert_eq!(app.pace, Pace::Normal);
+        app.update_pace(at(31));
+        assert_eq!(app.pace, Pace::Slow);
+        assert_eq!(app.polling_line(), "polling:   slow 5 s");
+
+        // Unfocused terminals are read slowly too
+        app.busy_at = at(1);
+        app.handle_event(Event::FocusLost);
+        app.update_pace(at(2));
+        assert_eq!(app.pace, Pace::Slow);
+        app.handle_event(Event::FocusGained);
+        app.update_pace(at(2));
+        assert_eq!(app.pace, Pace::Normal);
+    }
+
+    #[test]
+    fn polling_threshold_unit() {
+        let config = config::parse("unit = \"F\"\n[polling]\nthreshold = 1.8").unwrap();
+        let mut app = App::from_config(&config, &cli::Args::default()).unwrap();
+        let start = Instant::now();
+        let at = |secs| start + Duration::from_secs(secs);
+
+        // 1.2°C per second is above the threshold of 1.8°F, whichever unit
+        // is shown
+        let history = app
+            .history
+            .entry("coretemp-isa-0000/Core 0".into())
+            .or_default();
+        history.push(at(0), 50.0);
+        history.push(at(1), 51.2);
+        for _ in 0..3 {
+            app.update_pace(at(1));
+            assert_eq!(app.pace, Pace::Fast);
+            app.perform(Action::ToggleUnit);
+        }
+
+        // 0.8°C per second is below it
+        let mut history = SensorHistory::default();
+        history.push(at(0), 50.0);
+        history.push(at(1), 50.8);
+        app.history
+            .insert("coretemp-isa-0000/Core 0".into(), history);
+        for _ in 0..3 {
+            app.update_pace(at(1));
+            assert_eq!(app.pace, Pace::Normal);
+            app.perform(Action::ToggleUnit);
+        }
+    }
+
+    #[test]
+    fn sensors_output() {
+        assert!(parse_adapters("").is_err());
+        assert!(parse_adapters("[1, 2]").is_err());
+        let json_str =
+            "{\"acpitz-acpi-0\": 1, \"k10temp-pci-00c3\": {\"Tctl\": {\"temp1_input\": 45.5}}}";
+        let adapters = parse_adapters(json_str).unwrap();
+        assert_eq!(adapters.len(), 1);
+        assert_eq!(adapters[0].inputs[0].temp, 45.5);
+
+        let output = "ERROR: Can't get value of subfeature temp3_input\n{\n}\n";
+        assert_eq!(skip_to_json(output), "{\n}\n");
+        assert_eq!(skip_to_json("garbage"), "garbage");
+    }
+
     #[test]
     fn sensors_t490() {
         let json_str: String = std::fs::read_to_string("testdata/sensors-t490").unwrap();
-        let adapters = parse_adapters(json_str.as_str());
+        let adapters = parse_adapters(json_str.as_str()).unwrap();
         assert_eq!(adapters.len(), 6);
 
         assert_eq!(adapters[0].name, "acpitz-acpi-0");
@@ -704,6 +3214,10 @@ mod tests {
         assert_eq!(adapters[1].name, "coretemp-isa-0000");
         assert_eq!(adapters[1].inputs[0].name, "Core 0");
         assert_eq!(adapters[1].inputs[0].temp, 47.0);
+        assert_eq!(adapters[1].inputs[0].key, "temp2_input");
+        assert_eq!(adapters[1].inputs[0].max(), Some(100.0));
+        assert_eq!(adapters[1].inputs[0].crit(), Some(100.0));
+        assert_eq!(adapters[1].inputs[0].raw.len(), 4);
         assert_eq!(adapters[1].inputs[1].name, "Core 1");
         assert_eq!(adapters[1].inputs[1].temp, 49.0);
         assert_eq!(adapters[1].inputs[2].name, "Core 2");
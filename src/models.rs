@@ -0,0 +1,184 @@
+use crate::thermal::PATH_THERMAL;
+use crate::Adapter;
+
+static PATH_DMI_PRODUCT_NAME: &str = "/sys/class/dmi/id/product_name";
+static PATH_DMI_PRODUCT_VERSION: &str = "/sys/class/dmi/id/product_version";
+
+/// Prefix of the hwmon adapter created by the thinkpad_acpi module
+pub static THINKPAD_ADAPTER_PREFIX: &str = "thinkpad-isa-";
+
+/// A laptop model and the names of its thinkpad_acpi temperature sensors.
+#[derive(Debug, PartialEq)]
+pub struct Model {
+    /// DMI product_version, e.g. "ThinkPad T490"
+    pub product_version: &'static str,
+    /// DMI product_name prefixes (machine types) to tell apart models that
+    /// share a product_version, e.g. the Intel and AMD variants. Empty
+    /// matches any machine type.
+    pub machine_types: &'static [&'static str],
+    /// Pairs of hwmon sensor key and human readable name
+    pub sensors: &'static [(&'static str, &'static str)],
+}
+
+// The layout most models follow according to the thinkpad_acpi documentation.
+// Sensors 2 and 3 and 9..16 depend on the model and have no generic name.
+static GENERIC_SENSORS: &[(&str, &str)] = &[
+    ("temp1", "CPU"),
+    ("temp4", "GPU"),
+    ("temp5", "Main battery"),
+    ("temp6", "Bay battery"),
+    ("temp7", "Main battery 2"),
+    ("temp8", "Bay battery 2"),
+];
+
+// More specific entries (with machine types) must come before the generic
+// entry for the same product_version. To add a model, compare the output of
+// `sensors` with the physical location of each sensor and add an entry here.
+static MODELS: &[Model] = &[
+    Model {
+        product_version: "ThinkPad T490",
+        machine_types: &[],
+        sensors: &[("temp1", "CPU"), ("temp2", "GPU"), ("temp5", "Fan")],
+    },
+    Model {
+        product_version: "ThinkPad T14s Gen 1",
+        machine_types: &["20UH", "20UJ"],
+        sensors: &[("temp1", "CPU"), ("temp2", "GPU")],
+    },
+    Model {
+        product_version: "ThinkPad T14s Gen 4",
+        machine_types: &["21F8", "21F9"],
+        sensors: &[
+            ("temp1", "CPU"),
+            ("temp2", "GPU"),
+            ("temp3", "System board"),
+            ("temp4", "GPU 2"),
+            ("temp6", "Ambient"),
+            ("temp7", "Battery"),
+        ],
+    },
+    Model {
+        product_version: "ThinkPad E14 Gen 6",
+        machine_types: &["21M7", "21M8"],
+        sensors: &[("temp1", "CPU"), ("temp2", "GPU"), ("temp5", "Fan")],
+    },
+    Model {
+        product_version: "ThinkPad P1 Gen 2",
+        machine_types: &[],
+        sensors: &[
+            ("temp1", "CPU"),
+            ("temp2", "GPU"),
+            ("temp3", "System board"),
+            ("temp4", "GPU 2"),
+            ("temp5", "Main battery"),
+        ],
+    },
+];
+
+impl Model {
+    /// Returns the human readable name of a thinkpad_acpi sensor key
+    pub fn sensor_name(&self, key: &str) -> Option<&'static str> {
+        self.sensors
+            .iter()
+            .find(|(k, _)| *k == key)
+            .map(|(_, name)| *name)
+    }
+}
+
+/// Finds the model matching the given DMI strings
+pub fn lookup(product_version: &str, product_name: &str) -> Option<&'static Model> {
+    let product_version = product_version.trim();
+    let product_name = product_name.trim();
+    MODELS.iter().find(|model| {
+        model.product_version.eq_ignore_ascii_case(product_version)
+            && (model.machine_types.is_empty()
+                || model
+                    .machine_types
+                    .iter()
+                    .any(|mt| product_name.starts_with(mt)))
+    })
+}
+
+/// Finds the model of the running laptop using `/sys/class/dmi/id`
+pub fn detect() -> Option<&'static Model> {
+    let product_version = std::fs::read_to_string(PATH_DMI_PRODUCT_VERSION).ok()?;
+    let product_name = std::fs::read_to_string(PATH_DMI_PRODUCT_NAME).unwrap_or_default();
+    lookup(&product_version, &product_name)
+}
+
+/// Sets the label of every thinkpad_acpi input that has a known name, both
+/// for the hwmon adapter and for `/proc/acpi/ibm/thermal`. Models
+/// not in the database fall back to the generic thinkpad_acpi layout.
+pub fn apply_sensor_names(model: Option<&Model>, adapters: &mut [Adapter]) {
+    for adapter in adapters
+        .iter_mut()
+        .filter(|a| a.name.starts_with(THINKPAD_ADAPTER_PREFIX) || a.name == PATH_THERMAL)
+    {
+        for input in &mut adapter.inputs {
+            let name = match model {
+                Some(model) => model.sensor_name(&input.name),
+                None => GENERIC_SENSORS
+                    .iter()
+                    .find(|(k, _)| *k == input.name)
+                    .map(|(_, name)| *name),
+            };
+            // lm-sensors already labels some inputs, e.g. temp1 as "CPU"
+            input.label = name
+                .filter(|name| *name != input.name)
+                .map(|name| name.to_string());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_adapters;
+
+    #[test]
+    fn lookup_by_product_version() {
+        let model = lookup("ThinkPad T490\n", "20N2000KGE\n").unwrap();
+        assert_eq!(model.product_version, "ThinkPad T490");
+        assert_eq!(model.sensor_name("temp5"), Some("Fan"));
+        assert_eq!(model.sensor_name("temp9"), None);
+    }
+
+    #[test]
+    fn lookup_by_machine_type() {
+        assert!(lookup("ThinkPad T14s Gen 4", "21F8CTO1WW").is_some());
+        // Intel variant shares the product_version
+        assert!(lookup("ThinkPad T14s Gen 4", "21BR0000US").is_none());
+        assert!(lookup("ThinkPad X1 Carbon", "20KH").is_none());
+    }
+
+    #[test]
+    fn apply_names_t14s_amd_gen4() {
+        let json_str = std::fs::read_to_string("testdata/sensors-t14s-amd-gen4").unwrap();
+        let mut adapters = parse_adapters(json_str.as_str()).unwrap();
+        apply_sensor_names(lookup("ThinkPad T14s Gen 4", "21F8"), &mut adapters);
+
+        let thinkpad = &adapters[5];
+        assert_eq!(thinkpad.inputs[0].name, "CPU");
+        assert_eq!(thinkpad.inputs[0].label, None);
+        assert_eq!(thinkpad.inputs[1].display_name(), "System board (temp3)");
+        assert_eq!(thinkpad.inputs[2].display_name(), "Ambient (temp6)");
+        assert_eq!(thinkpad.inputs[3].display_name(), "Battery (temp7)");
+
+        // Other adapters are left untouched
+        assert!(adapters[..5]
+            .iter()
+            .all(|a| a.inputs.iter().all(|i| i.label.is_none())));
+    }
+
+    #[test]
+    fn apply_names_generic() {
+        let json_str = std::fs::read_to_string("testdata/sensors-t14s-amd-gen4").unwrap();
+        let mut adapters = parse_adapters(json_str.as_str()).unwrap();
+        apply_sensor_names(None, &mut adapters);
+
+        let thinkpad = &adapters[5];
+        assert_eq!(thinkpad.inputs[1].label, None);
+        assert_eq!(thinkpad.inputs[2].display_name(), "Bay battery (temp6)");
+        assert_eq!(thinkpad.inputs[3].display_name(), "Main battery 2 (temp7)");
+    }
+}
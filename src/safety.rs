@@ -0,0 +1,162 @@
+use std::time::Duration;
+
+use serde::Deserialize;
+
+use crate::units::Unit;
+
+/// Lowest safe levels used when none are configured, as (°C, level)
+const DEFAULT_MIN_LEVELS: &[(f64, u8)] = &[(70.0, 1), (85.0, 3)];
+
+/// What happens to a level below the safe level
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Policy {
+    Off,
+    /// Ask before writing it
+    #[default]
+    Confirm,
+    Refuse,
+}
+
+/// Level that is the least safe one from a temperature on
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct MinLevel {
+    /// Temperature of the hottest sensor, in the configured unit
+    pub temp: f64,
+    pub level: u8,
+}
+
+/// The `[safety]` section of the config file
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SafetyConfig {
+    pub policy: Policy,
+    /// Replaces the default levels if given
+    pub min_levels: Option<Vec<MinLevel>>,
+    /// Minutes after which a manual level goes back to auto
+    pub revert: Option<u64>,
+}
+
+/// Checks manual fan levels against the hottest temperature
+#[derive(Debug)]
+pub struct Safety {
+    policy: Policy,
+    /// Temperatures in °C and their lowest safe level
+    min_levels: Vec<(f64, u8)>,
+    revert: Option<Duration>,
+}
+
+impl Default for Safety {
+    fn default() -> Self {
+        Self::new(&SafetyConfig::default(), Unit::default()).expect("default levels are valid")
+    }
+}
+
+impl Safety {
+    pub fn new(config: &SafetyConfig, unit: Unit) -> Result<Self, String> {
+        let min_levels = match &config.min_levels {
+            Some(levels) => levels
+                .iter()
+                .map(|l| (unit.to_celsius(l.temp), l.level))
+                .collect(),
+            None => DEFAULT_MIN_LEVELS.to_vec(),
+        };
+        if let Some((_, level)) = min_levels.iter().find(|(_, level)| *level > 7) {
+            return Err(format!("safe levels must be at most 7, got {}", level));
+        }
+        Ok(Self {
+            policy: config.policy,
+            min_levels,
+            revert: config
+                .revert
+                .map(|minutes| Duration::from_secs(minutes * 60)),
+        })
+    }
+
+    pub fn policy(&self) -> Policy {
+        self.policy
+    }
+
+    /// Time after which manual levels go back to auto
+    pub fn revert(&self) -> Option<Duration> {
+        self.revert
+    }
+
+    /// Lowest safe level at a temperature in °C
+    pub fn min_level(&self, temp: f64) -> u8 {
+        self.min_levels
+            .iter()
+            .filter(|(from, _)| temp >= *from)
+            .map(|(_, level)| *level)
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// Lowest safe level if a fan command sets a level below it at the given
+    /// hottest temperature in °C. Auto and full speed are always safe.
+    pub fn check(&self, command: &str, temp: f64) -> Option<u8> {
+        let level = match command {
+            "disable" => 0,
+            command => command.strip_prefix("level ")?.parse().ok()?,
+        };
+        let min_level = self.min_level(temp);
+        (self.policy != Policy::Off && level < min_level).then_some(min_level)
+    }
+}
+
+/// Whether a fan command takes the fan out of automatic control
+pub fn is_manual(command: &str) -> bool {
+    command == "disable" || command.starts_with("level ") && command != "level auto"
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_levels() {
+        let safety = Safety::default();
+        assert_eq!(safety.check("level 0", 65.0), None);
+        assert_eq!(safety.check("level 0", 90.0), Some(3));
+        assert_eq!(safety.check("disable", 72.0), Some(1));
+        assert_eq!(safety.check("level 2", 86.0), Some(3));
+        assert_eq!(safety.check("level 3", 99.0), None);
+        assert_eq!(safety.check("level auto", 99.0), None);
+        assert_eq!(safety.check("level full-speed", 99.0), None);
+        assert_eq!(safety.revert(), None);
+    }
+
+    #[test]
+    fn configured_levels() {
+        let config: SafetyConfig = toml::from_str(
+            "policy = \"refuse\"\nrevert = 15\n\
+             min_levels = [{ temp = 140, level = 2 }, { temp = 176, level = 7 }]",
+        )
+        .unwrap();
+        let safety = Safety::new(&config, Unit::Fahrenheit).unwrap();
+        assert_eq!(safety.policy(), Policy::Refuse);
+        assert_eq!(safety.min_level(59.0), 0);
+        assert_eq!(safety.min_level(60.0), 2);
+        assert_eq!(safety.min_level(80.0), 7);
+        assert_eq!(safety.revert(), Some(Duration::from_secs(900)));
+
+        let config: SafetyConfig = toml::from_str("policy = \"off\"").unwrap();
+        let safety = Safety::new(&config, Unit::Celsius).unwrap();
+        assert_eq!(safety.check("level 0", 99.0), None);
+
+        let config: SafetyConfig =
+            toml::from_str("min_levels = [{ temp = 80, level = 9 }]").unwrap();
+        assert!(Safety::new(&config, Unit::Celsius).is_err());
+    }
+
+    #[test]
+    fn manual_commands() {
+        assert!(is_manual("level 3"));
+        assert!(is_manual("level full-speed"));
+        assert!(is_manual("disable"));
+        assert!(!is_manual("level auto"));
+        assert!(!is_manual("enable"));
+        assert!(!is_manual("watchdog 120"));
+    }
+}
@@ -0,0 +1,228 @@
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::thread;
+use std::time::Duration;
+
+use serde::Deserialize;
+
+use crate::daemons::{self, Daemon};
+use crate::error::Error;
+use crate::{thermal, Adapter};
+
+/// Shortest time between readings, `sensors` takes a while to run
+const MIN_INTERVAL: u64 = 100;
+
+/// Time temperatures have to stay calm before readings slow down
+pub const STABLE_AFTER: Duration = Duration::from_secs(30);
+
+/// The `[polling]` section of the config file
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PollingConfig {
+    /// Milliseconds between readings of the sensors and the fan
+    pub interval: u64,
+    /// Milliseconds between readings while temperatures change quickly or
+    /// an alert is active
+    pub fast: u64,
+    /// Milliseconds between readings while temperatures are stable or the
+    /// terminal is not focused
+    pub slow: u64,
+    /// Change per second, in the configured unit, from which temperatures
+    /// change quickly
+    pub threshold: f64,
+}
+
+impl Default for PollingConfig {
+    fn default() -> Self {
+        Self {
+            interval: 1000,
+            fast: 250,
+            slow: 5000,
+            threshold: 1.0,
+        }
+    }
+}
+
+impl PollingConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.fast < MIN_INTERVAL {
+            return Err(format!(
+                "polling interval must be at least {} ms, got {}",
+                MIN_INTERVAL, self.fast
+            ));
+        }
+        if self.fast > self.interval || self.interval > self.slow {
+            return Err(format!(
+                "polling intervals must be fast <= interval <= slow, got {} <= {} <= {}",
+                self.fast, self.interval, self.slow
+            ));
+        }
+        if self.threshold.is_nan() || self.threshold <= 0.0 {
+            return Err(format!(
+                "polling threshold must be above 0, got {}",
+                self.threshold
+            ));
+        }
+        Ok(())
+    }
+
+    /// Time between readings at a pace
+    pub fn interval(&self, pace: Pace) -> Duration {
+        Duration::from_millis(match pace {
+            Pace::Fast => self.fast,
+            Pace::Normal => self.interval,
+            Pace::Slow => self.slow,
+        })
+    }
+}
+
+/// How often readings are taken
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Pace {
+    Fast,
+    Normal,
+    Slow,
+}
+
+impl Pace {
+    /// Fast while something is going on, which wins over an unfocused
+    /// terminal so that alerts stay timely. Slow once temperatures were
+    /// calm for `STABLE_AFTER` or nobody is looking.
+    pub fn choose(busy: bool, calm_for: Duration, focused: bool) -> Self {
+        if busy {
+            Pace::Fast
+        } else if !focused || calm_for >= STABLE_AFTER {
+            Pace::Slow
+        } else {
+            Pace::Normal
+        }
+    }
+}
+
+/// What the sampler thread should read
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct Request {
+    pub sensors: bool,
+    pub fan: bool,
+    pub daemons: bool,
+}
+
+/// Readings of one request, `None` for the parts that were not requested
+#[derive(Debug, Default)]
+pub struct Snapshot {
+    /// Output of `sensors -j`
+    pub sensors: Option<Result<String, Error>>,
+    /// EC temperatures, read together with the sensors
+    pub thermal: Option<Adapter>,
+    /// Content of `/proc/acpi/ibm/fan`
+    pub fan: Option<Result<String, Error>>,
+    /// Running fan daemons, looking for them runs systemctl
+    pub daemons: Option<Vec<Daemon>>,
+}
+
+/// Thread reading the sensors and the fan, so that a slow `sensors` does
+/// not block drawing and input. It exits when the sampler is dropped.
+#[derive(Debug)]
+pub struct Sampler {
+    requests: Sender<Request>,
+    snapshots: Receiver<Snapshot>,
+}
+
+impl Sampler {
+    pub fn spawn(sample: impl Fn(Request) -> Snapshot + Send + 'static) -> Self {
+        let (requests, request_receiver) = mpsc::channel();
+        let (snapshot_sender, snapshots) = mpsc::channel();
+        thread::spawn(move || {
+            for request in request_receiver {
+                if snapshot_sender.send(sample(request)).is_err() {
+                    break;
+                }
+            }
+        });
+        Self {
+            requests,
+            snapshots,
+        }
+    }
+
+    pub fn request(&self, request: Request) {
+        // The thread only stops when the sampler is dropped
+        let _ = self.requests.send(request);
+    }
+
+    /// A snapshot that was taken since the last call, if any
+    pub fn try_recv(&self) -> Option<Snapshot> {
+        self.snapshots.try_recv().ok()
+    }
+}
+
+/// Reads the requested parts from the system
+pub fn sample(request: Request) -> Snapshot {
+    Snapshot {
+        sensors: request.sensors.then(crate::read_sensors),
+        thermal: request.sensors.then(thermal::read_thermal).flatten(),
+        fan: request.fan.then(crate::read_fan_file),
+        daemons: request.daemons.then(daemons::detect),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sampler_thread() {
+        let sampler = Sampler::spawn(|request| Snapshot {
+            sensors: request.sensors.then(|| Ok("{}".to_string())),
+            thermal: None,
+            fan: request
+                .fan
+                .then_some(Err(Error::FanFormat("fewer than 3 lines"))),
+            daemons: request.daemons.then(Vec::new),
+        });
+        assert!(sampler.try_recv().is_none());
+        sampler.request(Request {
+            sensors: true,
+            ..Request::default()
+        });
+        let snapshot = sampler
+            .snapshots
+            .recv_timeout(Duration::from_secs(5))
+            .unwrap();
+        assert_eq!(snapshot.sensors, Some(Ok("{}".to_string())));
+        assert!(snapshot.fan.is_none());
+    }
+
+    #[test]
+    fn interval() {
+        let config: PollingConfig = toml::from_str("interval = 2000\nslow = 10000").unwrap();
+        assert_eq!(config.validate(), Ok(()));
+        assert_eq!(config.interval(Pace::Fast), Duration::from_millis(250));
+        assert_eq!(config.interval(Pace::Normal), Duration::from_millis(2000));
+        assert_eq!(config.interval(Pace::Slow), Duration::from_millis(10000));
+        let too_short = PollingConfig {
+            fast: 10,
+            ..PollingConfig::default()
+        };
+        assert!(too_short.validate().is_err());
+        let unordered = PollingConfig {
+            slow: 500,
+            ..PollingConfig::default()
+        };
+        assert!(unordered.validate().is_err());
+        let threshold = PollingConfig {
+            threshold: f64::NAN,
+            ..PollingConfig::default()
+        };
+        assert!(threshold.validate().is_err());
+    }
+
+    #[test]
+    fn pace() {
+        let calm = Duration::from_secs(5);
+        assert_eq!(Pace::choose(false, calm, true), Pace::Normal);
+        assert_eq!(Pace::choose(true, calm, true), Pace::Fast);
+        assert_eq!(Pace::choose(true, calm, false), Pace::Fast);
+        assert_eq!(Pace::choose(false, calm, false), Pace::Slow);
+        assert_eq!(Pace::choose(false, STABLE_AFTER, true), Pace::Slow);
+    }
+}
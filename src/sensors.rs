@@ -0,0 +1,471 @@
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::fmt;
+use std::marker::PhantomData;
+
+use serde::de::{self, Deserialize, Deserializer, IgnoredAny, MapAccess, SeqAccess, Visitor};
+use serde_json::Value;
+
+use crate::error::Error;
+
+#[derive(Debug, Default, Clone)]
+pub struct Input {
+    pub name: String,
+    /// Human readable name from the model database or an alias, if known
+    pub label: Option<String>,
+    pub temp: f64,
+    /// lm-sensors key of the reading, e.g. temp1_input
+    pub key: String,
+    /// All numeric lm-sensors values of the feature, e.g. temp1_max
+    pub raw: Vec<(String, f64)>,
+}
+
+impl Input {
+    pub fn display_name(&self) -> String {
+        match &self.label {
+            Some(label) => format!("{} ({})", label, self.name),
+            None => self.name.clone(),
+        }
+    }
+
+    fn raw_value(&self, suffix: &str) -> Option<f64> {
+        self.raw
+            .iter()
+            .find(|(key, _)| key.ends_with(suffix))
+            .map(|(_, value)| *value)
+    }
+
+    /// High limit reported by the driver
+    pub fn max(&self) -> Option<f64> {
+        self.raw_value("_max")
+    }
+
+    /// Critical limit reported by the driver
+    pub fn crit(&self) -> Option<f64> {
+        self.raw_value("_crit")
+    }
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct Adapter {
+    pub name: String,
+    pub inputs: Vec<Input>,
+}
+
+/// Entries of a JSON object in document order, `None` for any other value.
+/// Unlike `serde_json::Map` it keeps every entry of a duplicate key.
+struct Object<T>(Option<Vec<(String, T)>>);
+
+impl<'de, T: Deserialize<'de>> Deserialize<'de> for Object<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_any(ObjectVisitor(PhantomData))
+    }
+}
+
+struct ObjectVisitor<T>(PhantomData<T>);
+
+impl<'de, T: Deserialize<'de>> Visitor<'de> for ObjectVisitor<T> {
+    type Value = Object<T>;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("any JSON value")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+        let mut entries = Vec::new();
+        while let Some(entry) = map.next_entry()? {
+            entries.push(entry);
+        }
+        Ok(Object(Some(entries)))
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+        while seq.next_element::<IgnoredAny>()?.is_some() {}
+        Ok(Object(None))
+    }
+
+    fn visit_bool<E: de::Error>(self, _: bool) -> Result<Self::Value, E> {
+        Ok(Object(None))
+    }
+
+    fn visit_i64<E: de::Error>(self, _: i64) -> Result<Self::Value, E> {
+        Ok(Object(None))
+    }
+
+    fn visit_u64<E: de::Error>(self, _: u64) -> Result<Self::Value, E> {
+        Ok(Object(None))
+    }
+
+    fn visit_f64<E: de::Error>(self, _: f64) -> Result<Self::Value, E> {
+        Ok(Object(None))
+    }
+
+    fn visit_str<E: de::Error>(self, _: &str) -> Result<Self::Value, E> {
+        Ok(Object(None))
+    }
+
+    fn visit_unit<E: de::Error>(self) -> Result<Self::Value, E> {
+        Ok(Object(None))
+    }
+}
+
+/// Parses the output of `sensors -j` into the adapters with temperature
+/// inputs. Values that are not finite numbers are skipped, duplicate
+/// adapters and labels are numbered, e.g. "CPU #2".
+pub fn parse_adapters(json_str: &str) -> Result<Vec<Adapter>, Error> {
+    let json = replace_non_finite(json_str);
+    let Object(chips) = serde_json::from_str::<Object<Object<Value>>>(&json)
+        .map_err(|e| Error::SensorsOutput(e.to_string()))?;
+    let Some(mut chips) = chips else {
+        return Err(Error::SensorsOutput("not a JSON object".to_string()));
+    };
+    // Sorted by name, duplicates stay in the order they were printed
+    chips.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+    let mut adapters = Vec::new();
+    for (adapter_name, Object(features)) in chips {
+        // e.g. a chip without features printed as a string
+        let Some(mut features) = features else {
+            continue;
+        };
+        features.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+        let mut curr_inputs: Vec<Input> = Vec::new();
+        for (input_name, input) in &features {
+            // "Adapter": "ISA adapter"
+            let Some(input_obj) = input.as_object() else {
+                continue;
+            };
+            for (temp_key, value) in input_obj {
+                if !temp_key.starts_with("temp") || !temp_key.ends_with("_input") {
+                    continue;
+                }
+                let Some(temp) = value.as_f64().filter(|temp| temp.is_finite()) else {
+                    continue;
+                };
+                // The EC reports unconnected sensors as 0°C, other chips
+                // may really be that cold
+                if temp == 0.0 && adapter_name.starts_with("thinkpad-") {
+                    continue;
+                }
+
+                curr_inputs.push(Input {
+                    name: input_name.clone(),
+                    temp,
+                    key: temp_key.clone(),
+                    raw: input_obj
+                        .iter()
+                        .filter_map(|(key, value)| {
+                            Some((key.clone(), value.as_f64().filter(|v| v.is_finite())?))
+                        })
+                        .collect(),
+                    ..Default::default()
+                });
+            }
+        }
+
+        if curr_inputs.is_empty() {
+            continue;
+        }
+        number_duplicates(curr_inputs.iter_mut().map(|input| &mut input.name));
+
+        adapters.push(Adapter {
+            name: adapter_name,
+            inputs: curr_inputs,
+        });
+    }
+    number_duplicates(adapters.iter_mut().map(|adapter| &mut adapter.name));
+
+    Ok(adapters)
+}
+
+/// Appends " #2", " #3" and so on to repeated names, so that every sensor
+/// has its own id
+fn number_duplicates<'a>(names: impl Iterator<Item = &'a mut String>) {
+    let mut seen: HashMap<String, usize> = HashMap::new();
+    for name in names {
+        let count = seen.entry(name.clone()).or_default();
+        *count += 1;
+        if *count > 1 {
+            *name = format!("{} #{}", name, count);
+        }
+    }
+}
+
+/// lm-sensors prints unreadable values as `nan` or `inf`, which is not
+/// JSON. Replaces them with `null` outside of strings.
+pub fn replace_non_finite(json: &str) -> Cow<'_, str> {
+    let is_word = |c: char| c.is_ascii_alphanumeric() || matches!(c, '-' | '+' | '.');
+    let mut out = String::new();
+    let mut copied = 0;
+    let mut in_string = false;
+    let mut escaped = false;
+    let mut chars = json.char_indices().peekable();
+    while let Some((start, c)) = chars.next() {
+        if in_string {
+            match c {
+                _ if escaped => escaped = false,
+                '\\' => escaped = true,
+                '"' => in_string = false,
+                _ => {}
+            }
+            continue;
+        }
+        if c == '"' {
+            in_string = true;
+            continue;
+        }
+        if !is_word(c) {
+            continue;
+        }
+        let mut end = start + c.len_utf8();
+        while let Some(&(i, c)) = chars.peek() {
+            if !is_word(c) {
+                break;
+            }
+            end = i + c.len_utf8();
+            chars.next();
+        }
+        let word = json[start..end].trim_start_matches(['-', '+']);
+        if matches!(
+            word.to_ascii_lowercase().as_str(),
+            "nan" | "inf" | "infinity"
+        ) {
+            out.push_str(&json[copied..start]);
+            out.push_str("null");
+            copied = end;
+        }
+    }
+    if copied == 0 {
+        return Cow::Borrowed(json);
+    }
+    out.push_str(&json[copied..]);
+    Cow::Owned(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    fn fixture(name: &str) -> Vec<Adapter> {
+        let json_str = std::fs::read_to_string(format!("testdata/{}", name)).unwrap();
+        parse_adapters(&json_str).unwrap()
+    }
+
+    fn names_and_temps(adapter: &Adapter) -> Vec<(&str, f64)> {
+        adapter
+            .inputs
+            .iter()
+            .map(|i| (i.name.as_str(), i.temp))
+            .collect()
+    }
+
+    #[test]
+    fn non_finite_values() {
+        let adapters = fixture("sensors-nan");
+        assert_eq!(adapters.len(), 1);
+        assert_eq!(names_and_temps(&adapters[0]), vec![("Composite", 38.85)]);
+        assert_eq!(adapters[0].inputs[0].crit(), None);
+        assert_eq!(adapters[0].inputs[0].max(), Some(81.85));
+
+        assert_eq!(
+            replace_non_finite("{\"a\": 1}"),
+            Cow::Borrowed("{\"a\": 1}")
+        );
+        assert_eq!(
+            replace_non_finite("{\"nan\": -nan, \"inf\": [inf,+Infinity], \"b\":NaN}"),
+            "{\"nan\": null, \"inf\": [null,null], \"b\":null}"
+        );
+        // Escaped quotes do not end strings
+        assert_eq!(
+            replace_non_finite("{\"a\\\" nan\": nan}"),
+            "{\"a\\\" nan\": null}"
+        );
+    }
+
+    #[test]
+    fn integers() {
+        let adapters = fixture("sensors-integers");
+        assert_eq!(names_and_temps(&adapters[0]), vec![("edge", 42.0)]);
+        assert_eq!(adapters[0].inputs[0].crit(), Some(100.0));
+        assert_eq!(
+            names_and_temps(&adapters[1]),
+            vec![("Tccd1", 44.75), ("Tctl", 45.0)]
+        );
+        assert_eq!(adapters[1].inputs[0].max(), Some(95.0));
+    }
+
+    #[test]
+    fn duplicate_labels() {
+        let adapters = fixture("sensors-duplicates");
+        assert_eq!(adapters.len(), 2);
+        assert_eq!(adapters[0].name, "coretemp-isa-0000");
+        assert_eq!(
+            names_and_temps(&adapters[0]),
+            vec![("Core 0", 47.0), ("Core 0 #2", 52.0), ("Core 1", 49.0)]
+        );
+        assert_eq!(adapters[0].inputs[1].key, "temp6_input");
+        assert_eq!(adapters[1].name, "coretemp-isa-0000 #2");
+        assert_eq!(names_and_temps(&adapters[1]), vec![("Package id 1", 53.0)]);
+    }
+
+    #[test]
+    fn non_objects() {
+        let adapters = fixture("sensors-non-objects");
+        assert_eq!(adapters.len(), 1);
+        assert_eq!(adapters[0].name, "iwlwifi_1-virtual-0");
+        assert_eq!(names_and_temps(&adapters[0]), vec![("temp3", 45.0)]);
+        assert!(parse_adapters("\"sensors\"").is_err());
+        assert!(parse_adapters("null").is_err());
+    }
+
+    #[test]
+    fn zero_degrees() {
+        let adapters = fixture("sensors-zero");
+        // Only the EC uses 0°C for unconnected sensors
+        assert_eq!(names_and_temps(&adapters[0]), vec![("temp1", 0.0)]);
+        assert_eq!(names_and_temps(&adapters[1]), vec![("CPU", 45.0)]);
+    }
+
+    /// Feature label and its subfeatures as keys and JSON values, `None` for
+    /// a feature that is not an object
+    type Feature = (String, Option<Vec<(String, String)>>);
+    /// Adapter name and features, `None` for an adapter that is not an
+    /// object
+    type Tree = (String, Option<Vec<Feature>>);
+
+    /// JSON text of a value of a subfeature, including the values
+    /// lm-sensors prints for unreadable sensors
+    fn value() -> impl Strategy<Value = String> {
+        prop_oneof![
+            (-300.0..300.0f64).prop_map(|v| format!("{:.3}", v)),
+            any::<i16>().prop_map(|v| v.to_string()),
+            Just("0.000".to_string()),
+            Just("nan".to_string()),
+            Just("-inf".to_string()),
+            Just("null".to_string()),
+            Just("\"N/A\"".to_string()),
+        ]
+    }
+
+    /// Subfeatures of a feature, e.g. `{"temp1_input": 45.0}`
+    fn subfeatures() -> impl Strategy<Value = Vec<(String, String)>> {
+        let key = prop::sample::select(vec![
+            "temp1_input",
+            "temp1_max",
+            "temp2_input",
+            "temp2_crit",
+            "fan1_input",
+            "in0_input",
+        ]);
+        prop::collection::btree_map(key, value(), 0..4)
+            .prop_map(|map| map.into_iter().map(|(k, v)| (k.to_string(), v)).collect())
+    }
+
+    /// A feature, an object in most cases and a string or a number in some
+    fn feature() -> impl Strategy<Value = Option<Vec<(String, String)>>> {
+        prop_oneof![
+            4 => subfeatures().prop_map(Some),
+            1 => Just(None),
+        ]
+    }
+
+    /// An adapter as its name and its features, with names picked from a
+    /// few so that duplicates are common
+    fn adapter() -> impl Strategy<Value = Tree> {
+        let name = prop_oneof![
+            prop::sample::select(vec![
+                "thinkpad-isa-0000",
+                "coretemp-isa-0000",
+                "acpitz-acpi-0"
+            ])
+            .prop_map(str::to_string),
+            "[a-z_]{1,8}-[a-z]{3}-[0-9]{1,4}",
+        ];
+        let label = prop::sample::select(vec!["CPU", "Core 0", "temp1", "Composite", "Adapter"]);
+        let features = prop::collection::vec((label.prop_map(str::to_string), feature()), 0..5);
+        (
+            name,
+            prop_oneof![4 => features.prop_map(Some), 1 => Just(None)],
+        )
+    }
+
+    fn to_json(adapters: &[Tree]) -> String {
+        let object = |entries: Vec<String>| format!("{{{}}}", entries.join(","));
+        object(
+            adapters
+                .iter()
+                .map(|(name, features)| {
+                    let features = match features {
+                        None => "\"no features\"".to_string(),
+                        Some(features) => object(
+                            features
+                                .iter()
+                                .map(|(label, subfeatures)| {
+                                    let subfeatures = match subfeatures {
+                                        None => "\"ISA adapter\"".to_string(),
+                                        Some(subfeatures) => object(
+                                            subfeatures
+                                                .iter()
+                                                .map(|(k, v)| format!("{:?}: {}", k, v))
+                                                .collect(),
+                                        ),
+                                    };
+                                    format!("{:?}: {}", label, subfeatures)
+                                })
+                                .collect(),
+                        ),
+                    };
+                    format!("{:?}: {}", name, features)
+                })
+                .collect(),
+        )
+    }
+
+    proptest! {
+        #[test]
+        fn any_text(text in any::<String>()) {
+            let _ = parse_adapters(&text);
+        }
+
+        #[test]
+        fn generated_trees(adapters in prop::collection::vec(adapter(), 0..6)) {
+            let parsed = parse_adapters(&to_json(&adapters)).unwrap();
+
+            // One input per readable temperature
+            let expected: usize = adapters
+                .iter()
+                .filter_map(|(name, features)| Some((name, features.as_ref()?)))
+                .flat_map(|(name, features)| {
+                    features
+                        .iter()
+                        .filter_map(|(_, subfeatures)| subfeatures.as_ref())
+                        .flatten()
+                        .filter(move |(key, value)| {
+                            let temp = value.parse::<f64>();
+                            key.starts_with("temp")
+                                && key.ends_with("_input")
+                                && temp.is_ok_and(|temp| {
+                                    temp.is_finite() && (temp != 0.0 || !name.starts_with("thinkpad-"))
+                                })
+                        })
+                })
+                .count();
+            let inputs: Vec<&Input> = parsed.iter().flat_map(|a| &a.inputs).collect();
+            prop_assert_eq!(inputs.len(), expected);
+            prop_assert!(inputs.iter().all(|input| input.temp.is_finite()));
+
+            // Every sensor has its own id
+            let mut ids: Vec<String> = parsed
+                .iter()
+                .flat_map(|a| a.inputs.iter().map(move |i| format!("{}/{}", a.name, i.name)))
+                .collect();
+            let count = ids.len();
+            ids.sort();
+            ids.dedup();
+            prop_assert_eq!(ids.len(), count);
+            prop_assert!(parsed.iter().all(|adapter| !adapter.inputs.is_empty()));
+        }
+    }
+}
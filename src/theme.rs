@@ -0,0 +1,429 @@
+use std::collections::HashMap;
+
+use ratatui::style::{Color, Style};
+use serde::Deserialize;
+
+/// Colours used to draw the interface. The gradients have one colour for
+/// cool, warm and hot temperatures.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Theme {
+    /// Filled part of the bars and the temperature dots
+    pub bar: [Color; 3],
+    /// Empty part of the bars
+    pub bar_empty: [Color; 3],
+    pub text: Color,
+    pub border: Color,
+    /// Errors and alerts
+    pub alert: Color,
+    pub scrollbar: Color,
+}
+
+pub static DARK: Theme = Theme {
+    bar: [
+        Color::Rgb(165, 183, 0),  // #a5b700
+        Color::Rgb(227, 168, 43), // #e3a82b
+        Color::Rgb(204, 31, 26),  // #cc1f1a
+    ],
+    bar_empty: [
+        Color::Rgb(68, 68, 37), // #444425
+        Color::Rgb(94, 78, 40), // #5e4e28
+        Color::Rgb(76, 32, 32), // #4c2020
+    ],
+    text: Color::Reset,
+    border: Color::Reset,
+    alert: Color::Rgb(204, 31, 26), // #cc1f1a
+    scrollbar: Color::Gray,
+};
+
+pub static LIGHT: Theme = Theme {
+    bar: [
+        Color::Rgb(94, 130, 0),  // #5e8200
+        Color::Rgb(196, 128, 0), // #c48000
+        Color::Rgb(190, 20, 20), // #be1414
+    ],
+    bar_empty: [
+        Color::Rgb(214, 222, 184), // #d6deb8
+        Color::Rgb(240, 222, 186), // #f0deba
+        Color::Rgb(240, 200, 200), // #f0c8c8
+    ],
+    text: Color::Reset,
+    border: Color::Reset,
+    alert: Color::Rgb(190, 20, 20), // #be1414
+    scrollbar: Color::DarkGray,
+};
+
+/// Uses only the basic terminal colours, at their brightest
+pub static HIGH_CONTRAST: Theme = Theme {
+    bar: [Color::LightGreen, Color::LightYellow, Color::LightRed],
+    bar_empty: [Color::DarkGray, Color::DarkGray, Color::DarkGray],
+    text: Color::White,
+    border: Color::White,
+    alert: Color::LightRed,
+    scrollbar: Color::White,
+};
+
+/// Blue to yellow, after the viridis palette, readable with colour blindness
+pub static VIRIDIS: Theme = Theme {
+    bar: [
+        Color::Rgb(49, 104, 142), // #31688e
+        Color::Rgb(53, 183, 121), // #35b779
+        Color::Rgb(253, 231, 37), // #fde725
+    ],
+    bar_empty: [
+        Color::Rgb(26, 42, 56), // #1a2a38
+        Color::Rgb(27, 64, 48), // #1b4030
+        Color::Rgb(79, 72, 20), // #4f4814
+    ],
+    text: Color::Reset,
+    border: Color::Reset,
+    alert: Color::Rgb(253, 231, 37), // #fde725
+    scrollbar: Color::Gray,
+};
+
+// Variants for terminals with only the 16 basic colours, where the closest
+// colours would lose the gradient
+
+static DARK_16: Theme = Theme {
+    bar: [Color::Green, Color::Yellow, Color::Red],
+    bar_empty: [Color::DarkGray, Color::DarkGray, Color::DarkGray],
+    alert: Color::Red,
+    ..DARK
+};
+
+static LIGHT_16: Theme = Theme {
+    bar: [Color::Green, Color::Yellow, Color::Red],
+    bar_empty: [Color::Gray, Color::Gray, Color::Gray],
+    alert: Color::Red,
+    ..LIGHT
+};
+
+static VIRIDIS_16: Theme = Theme {
+    bar: [Color::Blue, Color::Cyan, Color::LightYellow],
+    bar_empty: [Color::DarkGray, Color::DarkGray, Color::DarkGray],
+    alert: Color::LightYellow,
+    ..VIRIDIS
+};
+
+/// Built-in themes by name, with their 16 colour variant
+pub static BUILTIN: &[(&str, &Theme, &Theme)] = &[
+    ("dark", &DARK, &DARK_16),
+    ("light", &LIGHT, &LIGHT_16),
+    ("high-contrast", &HIGH_CONTRAST, &HIGH_CONTRAST),
+    ("viridis", &VIRIDIS, &VIRIDIS_16),
+];
+
+impl Default for Theme {
+    fn default() -> Self {
+        DARK
+    }
+}
+
+/// Index into the gradients for a position on the 0-100°C scale, given as a
+/// fraction
+pub fn level(ratio: f64) -> usize {
+    if ratio < 0.45 {
+        0
+    } else if ratio < 0.75 {
+        1
+    } else {
+        2
+    }
+}
+
+/// Index into the gradients for a temperature in °C
+pub fn temp_level(temp: f64) -> usize {
+    level((temp / 100.0).clamp(0.0, 1.0))
+}
+
+impl Theme {
+    /// Colour of the dot next to a temperature
+    pub fn temp_color(&self, temp: f64) -> Color {
+        self.bar[temp_level(temp)]
+    }
+
+    pub fn text_style(&self) -> Style {
+        Style::default().fg(self.text)
+    }
+
+    pub fn border_style(&self) -> Style {
+        Style::default().fg(self.border)
+    }
+
+    /// Replaces colours the terminal cannot show with the closest ones it can
+    pub fn with_support(mut self, support: ColorSupport) -> Self {
+        let convert = |color: &mut Color| *color = support.convert(*color);
+        self.bar.iter_mut().for_each(convert);
+        self.bar_empty.iter_mut().for_each(convert);
+        for color in [
+            &mut self.text,
+            &mut self.border,
+            &mut self.alert,
+            &mut self.scrollbar,
+        ] {
+            convert(color);
+        }
+        self
+    }
+}
+
+/// A user theme from the `[themes.<name>]` section of the config file.
+/// Colours not given are taken from the base theme.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ThemeConfig {
+    /// Name of a built-in theme, dark if not given
+    pub base: Option<String>,
+    pub bar: Option<[Color; 3]>,
+    pub bar_empty: Option<[Color; 3]>,
+    pub text: Option<Color>,
+    pub border: Option<Color>,
+    pub alert: Option<Color>,
+    pub scrollbar: Option<Color>,
+}
+
+fn builtin(name: &str, support: ColorSupport) -> Result<Theme, String> {
+    let (_, theme, theme_16) = BUILTIN.iter().find(|(n, _, _)| *n == name).ok_or_else(|| {
+        let names: Vec<&str> = BUILTIN.iter().map(|(n, _, _)| *n).collect();
+        format!(
+            "unknown theme '{}', expected one of: {}",
+            name,
+            names.join(", ")
+        )
+    })?;
+    Ok(match support {
+        ColorSupport::Ansi16 => **theme_16,
+        _ => theme.with_support(support),
+    })
+}
+
+/// Finds a theme by name and adapts it to the colours the terminal
+/// supports. User themes take precedence over the built-in ones.
+pub fn find(
+    name: &str,
+    themes: &HashMap<String, ThemeConfig>,
+    support: ColorSupport,
+) -> Result<Theme, String> {
+    let Some(config) = themes.get(name) else {
+        return builtin(name, support);
+    };
+    let base = builtin(config.base.as_deref().unwrap_or("dark"), support)?;
+    let color = |color: Option<Color>, base: Color| color.map_or(base, |c| support.convert(c));
+    let colors = |colors: Option<[Color; 3]>, base: [Color; 3]| {
+        colors.map_or(base, |c| c.map(|c| support.convert(c)))
+    };
+    Ok(Theme {
+        bar: colors(config.bar, base.bar),
+        bar_empty: colors(config.bar_empty, base.bar_empty),
+        text: color(config.text, base.text),
+        border: color(config.border, base.border),
+        alert: color(config.alert, base.alert),
+        scrollbar: color(config.scrollbar, base.scrollbar),
+    })
+}
+
+/// Colours the terminal can show
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColorSupport {
+    Ansi16,
+    Ansi256,
+    TrueColor,
+}
+
+impl ColorSupport {
+    /// Guesses the colour support from the `COLORTERM` and `TERM` variables
+    pub fn detect(colorterm: Option<&str>, term: Option<&str>) -> Self {
+        let term = term.unwrap_or("");
+        if matches!(colorterm, Some("truecolor" | "24bit")) || term.contains("direct") {
+            ColorSupport::TrueColor
+        } else if term.contains("256color") {
+            ColorSupport::Ansi256
+        } else {
+            ColorSupport::Ansi16
+        }
+    }
+
+    pub fn from_env() -> Self {
+        let colorterm = std::env::var("COLORTERM").ok();
+        let term = std::env::var("TERM").ok();
+        Self::detect(colorterm.as_deref(), term.as_deref())
+    }
+
+    fn convert(self, color: Color) -> Color {
+        match (self, color) {
+            (ColorSupport::TrueColor, _) => color,
+            (ColorSupport::Ansi256, Color::Rgb(r, g, b)) => Color::Indexed(ansi256(r, g, b)),
+            (ColorSupport::Ansi16, Color::Rgb(r, g, b)) => ansi16(r, g, b),
+            (ColorSupport::Ansi16, Color::Indexed(index)) if index >= 16 => {
+                let (r, g, b) = indexed_rgb(index);
+                ansi16(r, g, b)
+            }
+            _ => color,
+        }
+    }
+}
+
+/// Levels of the 6x6x6 colour cube of 256 colour terminals
+const CUBE_LEVELS: [u8; 6] = [0, 95, 135, 175, 215, 255];
+
+/// The 16 basic colours with the values xterm uses for them
+const ANSI16: [(Color, (u8, u8, u8)); 16] = [
+    (Color::Black, (0, 0, 0)),
+    (Color::Red, (205, 0, 0)),
+    (Color::Green, (0, 205, 0)),
+    (Color::Yellow, (205, 205, 0)),
+    (Color::Blue, (0, 0, 238)),
+    (Color::Magenta, (205, 0, 205)),
+    (Color::Cyan, (0, 205, 205)),
+    (Color::Gray, (229, 229, 229)),
+    (Color::DarkGray, (127, 127, 127)),
+    (Color::LightRed, (255, 0, 0)),
+    (Color::LightGreen, (0, 255, 0)),
+    (Color::LightYellow, (255, 255, 0)),
+    (Color::LightBlue, (92, 92, 255)),
+    (Color::LightMagenta, (255, 0, 255)),
+    (Color::LightCyan, (0, 255, 255)),
+    (Color::White, (255, 255, 255)),
+];
+
+fn distance(a: (u8, u8, u8), b: (u8, u8, u8)) -> u32 {
+    let d = |x: u8, y: u8| (x as i32 - y as i32).pow(2) as u32;
+    d(a.0, b.0) + d(a.1, b.1) + d(a.2, b.2)
+}
+
+/// Closest colour of the 256 colour palette, from the colour cube or the
+/// grayscale ramp
+fn ansi256(r: u8, g: u8, b: u8) -> u8 {
+    let cube_index = |v: u8| {
+        CUBE_LEVELS
+            .iter()
+            .enumerate()
+            .min_by_key(|(_, level)| (v as i32 - **level as i32).abs())
+            .map(|(i, _)| i as u8)
+            .unwrap()
+    };
+    let (ri, gi, bi) = (cube_index(r), cube_index(g), cube_index(b));
+    let cube = 16 + 36 * ri + 6 * gi + bi;
+
+    let avg = (r as u16 + g as u16 + b as u16) / 3;
+    let gray = (avg.saturating_sub(3) / 10).min(23) as u8;
+    let gray_value = 8 + 10 * gray;
+
+    if distance((r, g, b), (gray_value, gray_value, gray_value))
+        < distance((r, g, b), indexed_rgb(cube))
+    {
+        232 + gray
+    } else {
+        cube
+    }
+}
+
+/// Closest of the 16 basic colours
+fn ansi16(r: u8, g: u8, b: u8) -> Color {
+    ANSI16
+        .iter()
+        .min_by_key(|(_, rgb)| distance((r, g, b), *rgb))
+        .map(|(color, _)| *color)
+        .unwrap()
+}
+
+/// Value of a 256 colour palette entry
+fn indexed_rgb(index: u8) -> (u8, u8, u8) {
+    match index {
+        0..=15 => ANSI16[index as usize].1,
+        16..=231 => {
+            let i = index - 16;
+            (
+                CUBE_LEVELS[(i / 36) as usize],
+                CUBE_LEVELS[(i / 6 % 6) as usize],
+                CUBE_LEVELS[(i % 6) as usize],
+            )
+        }
+        _ => {
+            let value = 8 + 10 * (index - 232);
+            (value, value, value)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn levels() {
+        assert_eq!(DARK.temp_color(30.0), DARK.bar[0]);
+        assert_eq!(DARK.temp_color(60.0), DARK.bar[1]);
+        assert_eq!(DARK.temp_color(75.0), DARK.bar[2]);
+        assert_eq!(DARK.temp_color(130.0), DARK.bar[2]);
+        assert_eq!(DARK.temp_color(-10.0), DARK.bar[0]);
+    }
+
+    #[test]
+    fn find_themes() {
+        let themes = HashMap::new();
+        let truecolor = ColorSupport::TrueColor;
+        assert_eq!(find("viridis", &themes, truecolor), Ok(VIRIDIS));
+        assert_eq!(find("dark", &themes, ColorSupport::Ansi16), Ok(DARK_16));
+        assert!(find("solarized", &themes, truecolor).is_err());
+
+        let config: HashMap<String, ThemeConfig> = toml::from_str(
+            r##"
+            [mine]
+            base = "light"
+            alert = "magenta"
+            bar = ["#000080", "12", "Blue"]
+            "##,
+        )
+        .unwrap();
+        let theme = find("mine", &config, truecolor).unwrap();
+        assert_eq!(theme.alert, Color::Magenta);
+        assert_eq!(
+            theme.bar,
+            [Color::Rgb(0, 0, 128), Color::Indexed(12), Color::Blue]
+        );
+        assert_eq!(theme.bar_empty, LIGHT.bar_empty);
+
+        let config: HashMap<String, ThemeConfig> =
+            toml::from_str("[mine]\nbase = \"sepia\"\n").unwrap();
+        assert!(find("mine", &config, truecolor).is_err());
+
+        let config: HashMap<String, ThemeConfig> =
+            toml::from_str("[mine]\nalert = \"#ff0000\"\n").unwrap();
+        let theme = find("mine", &config, ColorSupport::Ansi256).unwrap();
+        assert_eq!(theme.alert, Color::Indexed(196));
+        assert_eq!(theme.bar[0], Color::Indexed(142));
+    }
+
+    #[test]
+    fn detect_support() {
+        use ColorSupport::*;
+        assert_eq!(
+            ColorSupport::detect(Some("truecolor"), Some("xterm-256color")),
+            TrueColor
+        );
+        assert_eq!(ColorSupport::detect(None, Some("xterm-direct")), TrueColor);
+        assert_eq!(ColorSupport::detect(None, Some("xterm-256color")), Ansi256);
+        assert_eq!(ColorSupport::detect(None, Some("linux")), Ansi16);
+        assert_eq!(ColorSupport::detect(None, None), Ansi16);
+    }
+
+    #[test]
+    fn fallback_colors() {
+        assert_eq!(ansi256(255, 0, 0), 196);
+        assert_eq!(ansi256(0, 0, 0), 16);
+        assert_eq!(ansi256(128, 128, 128), 244);
+        assert_eq!(ansi256(165, 183, 0), 142);
+        assert_eq!(ansi16(204, 31, 26), Color::Red);
+        assert_eq!(ansi16(253, 231, 37), Color::LightYellow);
+
+        assert_eq!(
+            HIGH_CONTRAST.with_support(ColorSupport::Ansi16),
+            HIGH_CONTRAST
+        );
+        assert_eq!(
+            ColorSupport::Ansi16.convert(Color::Indexed(196)),
+            Color::LightRed
+        );
+        assert_eq!(DARK.with_support(ColorSupport::TrueColor), DARK);
+    }
+}
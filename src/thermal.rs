@@ -0,0 +1,108 @@
+use crate::{Adapter, Input};
+
+/// EC thermal readings exposed by thinkpad_acpi, the same values thinkfan
+/// uses for its `tpacpi` sensor type.
+pub static PATH_THERMAL: &str = "/proc/acpi/ibm/thermal";
+
+// thinkpad_acpi reports missing sensors as -128
+const TEMP_NOT_AVAILABLE: i64 = -128;
+
+/// Parses the contents of `/proc/acpi/ibm/thermal` into an adapter with one
+/// input per available sensor. Inputs are named like the hwmon sensors of the
+/// thinkpad-isa adapter, i.e. `temp1` for the first reading.
+pub fn parse_thermal(content: &str) -> Option<Adapter> {
+    let line = content
+        .lines()
+        .find_map(|line| line.strip_prefix("temperatures:"))?;
+
+    let inputs: Vec<Input> = line
+        .split_whitespace()
+        .enumerate()
+        .filter_map(|(idx, value)| {
+            // "N/A" on some kernels
+            let temp = value.parse::<i64>().ok()?;
+            // Skip unconnected sensors the same way as parse_adapters does
+            if temp == TEMP_NOT_AVAILABLE || temp == 0 {
+                return None;
+            }
+            Some(Input {
+                name: format!("temp{}", idx + 1),
+                temp: temp as f64,
+                ..Default::default()
+            })
+        })
+        .collect();
+
+    if inputs.is_empty() {
+        return None;
+    }
+
+    Some(Adapter {
+        name: PATH_THERMAL.to_string(),
+        inputs,
+    })
+}
+
+/// Reads `/proc/acpi/ibm/thermal`, returns None if it is missing or empty
+pub fn read_thermal() -> Option<Adapter> {
+    let content = std::fs::read_to_string(PATH_THERMAL).ok()?;
+    parse_thermal(&content)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names_and_temps(adapter: &Adapter) -> Vec<(&str, f64)> {
+        adapter
+            .inputs
+            .iter()
+            .map(|i| (i.name.as_str(), i.temp))
+            .collect()
+    }
+
+    #[test]
+    fn thermal_t14s_amd_gen1() {
+        let content = std::fs::read_to_string("testdata/thermal-t14s-amd-gen1").unwrap();
+        let adapter = parse_thermal(&content).unwrap();
+        assert_eq!(adapter.name, PATH_THERMAL);
+        assert_eq!(names_and_temps(&adapter), vec![("temp1", 50.0)]);
+    }
+
+    #[test]
+    fn thermal_t14s_amd_gen4() {
+        let content = std::fs::read_to_string("testdata/thermal-t14s-amd-gen4").unwrap();
+        let adapter = parse_thermal(&content).unwrap();
+        assert_eq!(
+            names_and_temps(&adapter),
+            vec![
+                ("temp1", 45.0),
+                ("temp3", 45.0),
+                ("temp6", 45.0),
+                ("temp7", 45.0),
+                ("temp9", 38.0),
+            ]
+        );
+    }
+
+    #[test]
+    fn thermal_t490() {
+        let content = std::fs::read_to_string("testdata/thermal-t490").unwrap();
+        let adapter = parse_thermal(&content).unwrap();
+        assert_eq!(
+            names_and_temps(&adapter),
+            vec![("temp1", 46.0), ("temp5", 34.0)]
+        );
+    }
+
+    #[test]
+    fn thermal_not_available() {
+        assert!(parse_thermal("temperatures:\tN/A N/A -128\n").is_none());
+        assert!(parse_thermal("").is_none());
+        let adapter = parse_thermal("temperatures:\t51 N/A 40\n").unwrap();
+        assert_eq!(
+            names_and_temps(&adapter),
+            vec![("temp1", 51.0), ("temp3", 40.0)]
+        );
+    }
+}
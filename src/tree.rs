@@ -0,0 +1,331 @@
+use std::collections::HashSet;
+
+use crate::{Adapter, Input, Sorting};
+
+/// Name of the group listing the pinned sensors at the top of the tree
+pub static PINNED_GROUP: &str = "Pinned";
+
+/// Identifies a sensor across readings
+pub fn sensor_id(adapter: &Adapter, input: &Input) -> String {
+    format!("{}/{}", adapter.name, input.name)
+}
+
+/// How the user has arranged the tree
+#[derive(Debug, Default)]
+pub struct TreeState {
+    /// Names of the groups whose inputs are hidden
+    pub collapsed: HashSet<String>,
+    /// Ids of the sensors shown in the pinned group
+    pub pinned: HashSet<String>,
+    /// Ids of the sensors left out of the tree
+    pub hidden: HashSet<String>,
+    /// Show hidden sensors anyway, so that they can be unhidden
+    pub show_hidden: bool,
+}
+
+/// Summary of the inputs of one group
+#[derive(Debug, Clone, Copy)]
+pub struct GroupStats<'a> {
+    pub min: f64,
+    pub max: f64,
+    pub avg: f64,
+    pub hottest: &'a Input,
+}
+
+impl<'a> GroupStats<'a> {
+    /// Returns None for groups without inputs
+    pub fn new(inputs: &[(&'a Adapter, &'a Input)]) -> Option<Self> {
+        // First of the hottest inputs, matching the order when sorting by temp
+        let hottest =
+            inputs
+                .iter()
+                .map(|(_, input)| *input)
+                .reduce(|a, b| if b.temp > a.temp { b } else { a })?;
+        let min = inputs
+            .iter()
+            .map(|(_, i)| i.temp)
+            .fold(f64::INFINITY, f64::min);
+        let sum: f64 = inputs.iter().map(|(_, i)| i.temp).sum();
+        Some(Self {
+            min,
+            max: hottest.temp,
+            avg: sum / inputs.len() as f64,
+            hottest,
+        })
+    }
+}
+
+/// A row in the Temperatures panel, either a group header or one of its
+/// inputs. Groups are the adapters plus the pinned group.
+#[derive(Debug, Clone, Copy)]
+pub enum TreeRow<'a> {
+    Header {
+        name: &'a str,
+        count: usize,
+        stats: GroupStats<'a>,
+        collapsed: bool,
+    },
+    Input {
+        adapter: &'a Adapter,
+        input: &'a Input,
+        pinned: bool,
+        hidden: bool,
+    },
+}
+
+impl TreeRow<'_> {
+    /// Number of terminal lines the row occupies. Inputs use one line for the
+    /// label and one for the bar.
+    pub fn height(&self) -> usize {
+        match self {
+            TreeRow::Header { .. } => 1,
+            TreeRow::Input { .. } => 2,
+        }
+    }
+
+    /// Identifies the row across readings, the group name for headers and
+    /// the sensor id for inputs
+    pub fn id(&self) -> String {
+        match self {
+            TreeRow::Header { name, .. } => name.to_string(),
+            TreeRow::Input { adapter, input, .. } => sensor_id(adapter, input),
+        }
+    }
+}
+
+/// Name, inputs and summary of a group
+type Group<'a> = (&'a str, Vec<(&'a Adapter, &'a Input)>, GroupStats<'a>);
+
+/// Builds the visible rows of the tree. Pinned sensors are moved to a group
+/// at the top and inputs of collapsed groups are left out. When sorting by
+/// temperature the groups are ordered by their hottest input and the inputs
+/// by temperature, otherwise both keep the order reported by the sensors.
+pub fn build<'a>(
+    adapters: &'a [Adapter],
+    state: &TreeState,
+    sorting: &Sorting,
+) -> Vec<TreeRow<'a>> {
+    let is_hidden =
+        |adapter: &Adapter, input: &Input| state.hidden.contains(&sensor_id(adapter, input));
+    let is_pinned =
+        |adapter: &Adapter, input: &Input| state.pinned.contains(&sensor_id(adapter, input));
+
+    let pinned: Vec<(&Adapter, &Input)> = adapters
+        .iter()
+        .flat_map(|a| a.inputs.iter().map(move |i| (a, i)))
+        .filter(|(a, i)| is_pinned(a, i) && (state.show_hidden || !is_hidden(a, i)))
+        .collect();
+
+    let mut groups: Vec<Group> = adapters
+        .iter()
+        .filter_map(|adapter| {
+            let inputs: Vec<(&Adapter, &Input)> = adapter
+                .inputs
+                .iter()
+                .filter(|i| !is_pinned(adapter, i) && (state.show_hidden || !is_hidden(adapter, i)))
+                .map(|i| (adapter, i))
+                .collect();
+            let stats = GroupStats::new(&inputs)?;
+            Some((adapter.name.as_str(), inputs, stats))
+        })
+        .collect();
+
+    if let Sorting::Temp = sorting {
+        groups.sort_by(|a, b| b.2.max.total_cmp(&a.2.max));
+    }
+
+    if let Some(stats) = GroupStats::new(&pinned) {
+        groups.insert(0, (PINNED_GROUP, pinned, stats));
+    }
+
+    let mut rows = Vec::new();
+    for (name, mut inputs, stats) in groups {
+        let is_collapsed = state.collapsed.contains(name);
+        rows.push(TreeRow::Header {
+            name,
+            count: inputs.len(),
+            stats,
+            collapsed: is_collapsed,
+        });
+        if is_collapsed {
+            continue;
+        }
+
+        if let Sorting::Temp = sorting {
+            inputs.sort_by(|a, b| b.1.temp.total_cmp(&a.1.temp));
+        }
+        rows.extend(inputs.into_iter().map(|(adapter, input)| TreeRow::Input {
+            adapter,
+            input,
+            pinned: name == PINNED_GROUP,
+            hidden: is_hidden(adapter, input),
+        }));
+    }
+    rows
+}
+
+/// Total number of lines needed to show all rows
+pub fn total_height(rows: &[TreeRow]) -> usize {
+    rows.iter().map(|r| r.height()).sum()
+}
+
+/// Number of rows starting at `start` that fit completely within `height`
+/// lines
+pub fn rows_fitting(rows: &[TreeRow], start: usize, height: usize) -> usize {
+    let mut used = 0;
+    rows.iter()
+        .skip(start)
+        .take_while(|row| {
+            used += row.height();
+            used <= height
+        })
+        .count()
+}
+
+/// Largest scroll offset (in rows) that still fills the panel to the bottom
+pub fn max_scroll(rows: &[TreeRow], height: usize) -> usize {
+    let mut used = 0;
+    let mut start = rows.len();
+    while start > 0 && used + rows[start - 1].height() <= height {
+        start -= 1;
+        used += rows[start].height();
+    }
+    start
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_adapters;
+
+    fn adapters() -> Vec<Adapter> {
+        let json_str = std::fs::read_to_string("testdata/sensors-t490").unwrap();
+        parse_adapters(json_str.as_str()).unwrap()
+    }
+
+    fn names<'a>(rows: &[TreeRow<'a>]) -> Vec<&'a str> {
+        rows.iter()
+            .map(|row| match row {
+                TreeRow::Header { name, .. } => *name,
+                TreeRow::Input { input, .. } => input.name.as_str(),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn stats() {
+        let adapters = adapters();
+        let inputs: Vec<_> = adapters[1]
+            .inputs
+            .iter()
+            .map(|i| (&adapters[1], i))
+            .collect();
+        let stats = GroupStats::new(&inputs).unwrap();
+        assert_eq!(stats.min, 47.0);
+        assert_eq!(stats.max, 51.0);
+        assert_eq!(stats.avg, 49.4);
+        assert_eq!(stats.hottest.name, "Core 2");
+        assert!(GroupStats::new(&[]).is_none());
+    }
+
+    #[test]
+    fn build_by_name() {
+        let adapters = adapters();
+        let rows = build(&adapters, &TreeState::default(), &Sorting::Name);
+        assert_eq!(rows.len(), 6 + 13);
+        assert_eq!(
+            names(&rows[..4]),
+            vec!["acpitz-acpi-0", "temp1", "coretemp-isa-0000", "Core 0"]
+        );
+    }
+
+    #[test]
+    fn build_by_temp() {
+        let adapters = adapters();
+        let rows = build(&adapters, &TreeState::default(), &Sorting::Temp);
+        assert_eq!(
+            names(&rows[..4]),
+            vec![
+                "iwlwifi_1-virtual-0",
+                "temp1",
+                "coretemp-isa-0000",
+                "Core 2"
+            ]
+        );
+    }
+
+    #[test]
+    fn build_collapsed() {
+        let adapters = adapters();
+        let state = TreeState {
+            collapsed: HashSet::from(["coretemp-isa-0000".to_string()]),
+            ..Default::default()
+        };
+        let rows = build(&adapters, &state, &Sorting::Name);
+        assert_eq!(rows.len(), 6 + 8);
+        assert_eq!(
+            names(&rows[..4]),
+            vec![
+                "acpitz-acpi-0",
+                "temp1",
+                "coretemp-isa-0000",
+                "iwlwifi_1-virtual-0"
+            ]
+        );
+        assert!(matches!(
+            rows[2],
+            TreeRow::Header {
+                collapsed: true,
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn build_pinned_and_hidden() {
+        let adapters = adapters();
+        let mut state = TreeState {
+            pinned: HashSet::from(["nvme-pci-3d00/Sensor 2".to_string()]),
+            hidden: HashSet::from([
+                "acpitz-acpi-0/temp1".to_string(),
+                "coretemp-isa-0000/Core 0".to_string(),
+            ]),
+            ..Default::default()
+        };
+        let rows = build(&adapters, &state, &Sorting::Name);
+        assert_eq!(
+            names(&rows[..5]),
+            vec![
+                PINNED_GROUP,
+                "Sensor 2",
+                "coretemp-isa-0000",
+                "Core 1",
+                "Core 2"
+            ]
+        );
+        assert!(matches!(rows[1], TreeRow::Input { pinned: true, .. }));
+        assert_eq!(rows[1].id(), "nvme-pci-3d00/Sensor 2");
+        assert!(matches!(rows[2], TreeRow::Header { count: 4, .. }));
+        // The pinned sensor is not listed twice
+        assert_eq!(rows.len(), 1 + 1 + 5 + 10);
+
+        state.show_hidden = true;
+        let rows = build(&adapters, &state, &Sorting::Name);
+        assert_eq!(names(&rows[2..4]), vec!["acpitz-acpi-0", "temp1"]);
+        assert!(matches!(rows[3], TreeRow::Input { hidden: true, .. }));
+    }
+
+    #[test]
+    fn scrolling() {
+        let adapters = adapters();
+        let rows = build(&adapters, &TreeState::default(), &Sorting::Name);
+        assert_eq!(total_height(&rows), 6 + 13 * 2);
+        // Header and input at the top
+        assert_eq!(rows_fitting(&rows, 0, 3), 2);
+        assert_eq!(rows_fitting(&rows, 0, 4), 3);
+        // Last adapter is a header followed by two inputs
+        assert_eq!(max_scroll(&rows, 5), rows.len() - 3);
+        assert_eq!(max_scroll(&rows, 4), rows.len() - 2);
+        assert_eq!(max_scroll(&rows, 1000), 0);
+    }
+}
@@ -0,0 +1,110 @@
+use serde::Deserialize;
+
+/// Unit temperatures are shown in. Readings, thresholds and the control
+/// logic use Celsius, values are only converted for display.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Unit {
+    #[default]
+    #[serde(alias = "C")]
+    Celsius,
+    #[serde(alias = "F")]
+    Fahrenheit,
+    #[serde(alias = "K")]
+    Kelvin,
+}
+
+impl Unit {
+    /// Converts a temperature in °C to this unit
+    pub fn convert(self, temp: f64) -> f64 {
+        match self {
+            Unit::Celsius => temp,
+            Unit::Fahrenheit => temp * 9.0 / 5.0 + 32.0,
+            Unit::Kelvin => temp + 273.15,
+        }
+    }
+
+    /// Converts a temperature in this unit to °C
+    pub fn to_celsius(self, temp: f64) -> f64 {
+        match self {
+            Unit::Celsius => temp,
+            Unit::Fahrenheit => (temp - 32.0) * 5.0 / 9.0,
+            Unit::Kelvin => temp - 273.15,
+        }
+    }
+
+    /// Converts a temperature difference, such as a rate of change, in °C
+    /// to this unit
+    pub fn convert_delta(self, delta: f64) -> f64 {
+        match self {
+            Unit::Fahrenheit => delta * 9.0 / 5.0,
+            Unit::Celsius | Unit::Kelvin => delta,
+        }
+    }
+
+    /// Converts a temperature difference in this unit to °C
+    pub fn delta_to_celsius(self, delta: f64) -> f64 {
+        match self {
+            Unit::Fahrenheit => delta * 5.0 / 9.0,
+            Unit::Celsius | Unit::Kelvin => delta,
+        }
+    }
+
+    /// Symbol of the unit, without the degree sign for Kelvin. The degree
+    /// sign is passed in since it is left out in ASCII mode.
+    pub fn symbol(self, degree: &str) -> String {
+        match self {
+            Unit::Celsius => format!("{}C", degree),
+            Unit::Fahrenheit => format!("{}F", degree),
+            Unit::Kelvin => "K".to_string(),
+        }
+    }
+
+    /// The unit after this one when toggling
+    pub fn next(self) -> Self {
+        match self {
+            Unit::Celsius => Unit::Fahrenheit,
+            Unit::Fahrenheit => Unit::Kelvin,
+            Unit::Kelvin => Unit::Celsius,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn conversions() {
+        assert_eq!(Unit::Celsius.convert(45.5), 45.5);
+        assert_eq!(Unit::Fahrenheit.convert(100.0), 212.0);
+        assert_eq!(Unit::Fahrenheit.convert(-40.0), -40.0);
+        assert_eq!(Unit::Kelvin.convert(0.0), 273.15);
+        for unit in [Unit::Celsius, Unit::Fahrenheit, Unit::Kelvin] {
+            assert!((unit.to_celsius(unit.convert(63.2)) - 63.2).abs() < 1e-9);
+        }
+        assert_eq!(Unit::Fahrenheit.convert_delta(0.5), 0.9);
+        assert_eq!(Unit::Kelvin.convert_delta(0.5), 0.5);
+        assert_eq!(Unit::Fahrenheit.delta_to_celsius(0.9), 0.5);
+    }
+
+    #[test]
+    fn symbols_and_toggle() {
+        assert_eq!(Unit::Celsius.symbol("°"), "°C");
+        assert_eq!(Unit::Fahrenheit.symbol(""), "F");
+        assert_eq!(Unit::Kelvin.symbol("°"), "K");
+        assert_eq!(Unit::Celsius.next().next().next(), Unit::Celsius);
+    }
+
+    #[test]
+    fn deserialize() {
+        #[derive(Deserialize)]
+        struct Config {
+            unit: Unit,
+        }
+        let parse = |s: &str| toml::from_str::<Config>(s).map(|c| c.unit);
+        assert_eq!(parse("unit = \"fahrenheit\""), Ok(Unit::Fahrenheit));
+        assert_eq!(parse("unit = \"K\""), Ok(Unit::Kelvin));
+        assert!(parse("unit = \"rankine\"").is_err());
+    }
+}